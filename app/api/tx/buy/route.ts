@@ -1,11 +1,23 @@
 import { NextRequest, NextResponse } from 'next/server';
-import { Connection, PublicKey, Transaction, SystemProgram, SYSVAR_RENT_PUBKEY } from '@solana/web3.js';
-import { AnchorProvider, Program, Wallet, BN } from '@coral-xyz/anchor';
-import { TOKEN_PROGRAM_ID, getAssociatedTokenAddress, createAssociatedTokenAccountInstruction } from '@solana/spl-token';
+import { Connection, PublicKey, Transaction, SystemProgram } from '@solana/web3.js';
+import { AnchorProvider, Wallet, BN } from '@coral-xyz/anchor';
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+  createAssociatedTokenAccountInstruction,
+} from '@solana/spl-token';
+import {
+  MARKET_PROGRAM_ID,
+  REGISTRY_PROGRAM_ID,
+  getPrograms,
+  findRegistryPda,
+  findMarketPda,
+  feeAmount,
+  issuerFeeBps,
+  getMintTokenProgram,
+} from '@/lib/rwa-programs';
 
 const RPC_URL = process.env.NEXT_PUBLIC_RPC_URL || 'https://api.devnet.solana.com';
-const MARKET_PROGRAM_ID = new PublicKey('Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnT');
-const REGISTRY_PROGRAM_ID = new PublicKey('3ER5UsBMKiP81Giq45LD6dL5adF8vH8v2juiBGT4NHSg');
 
 export async function POST(request: NextRequest) {
   try {
@@ -23,16 +35,16 @@ export async function POST(request: NextRequest) {
     }
 
     // Валидация типов данных
-    if (typeof amount !== 'number' || amount <= 0) {
+    if (!Number.isSafeInteger(amount) || amount <= 0) {
       return NextResponse.json(
-        { error: 'Amount must be a positive number' },
+        { error: 'Amount must be a positive integer in base units' },
         { status: 400 }
       );
     }
 
-    if (maxPrice !== undefined && (typeof maxPrice !== 'number' || maxPrice <= 0)) {
+    if (maxPrice !== undefined && (!Number.isSafeInteger(maxPrice) || maxPrice <= 0)) {
       return NextResponse.json(
-        { error: 'Max price must be a positive number' },
+        { error: 'Max price must be a positive integer in USDC base units' },
         { status: 400 }
       );
     }
@@ -72,84 +84,88 @@ export async function POST(request: NextRequest) {
     const mintPubkey = new PublicKey(mint);
     const buyerPubkey = new PublicKey(userWallet);
     
-    // Находим PDA для актива
-    const [assetPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from('asset'), mintPubkey.toBuffer()],
-      REGISTRY_PROGRAM_ID
-    );
-    
-    // Находим PDA для mint authority
-    const [mintAuthPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from('mint_auth'), mintPubkey.toBuffer()],
-      MARKET_PROGRAM_ID
-    );
-    
-    // Находим PDA для USDC vault
-    const [vaultUsdcPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from('vault_usdc'), mintPubkey.toBuffer()],
-      MARKET_PROGRAM_ID
-    );
-    
+    const { market, registry } = getPrograms(provider);
+
+    // Находим PDA актива и читаем его вместе с конфигом платформы
+    const assetPda = findRegistryPda([Buffer.from('asset'), mintPubkey.toBuffer()]);
+    const platformConfigPda = findRegistryPda([Buffer.from('platform_config')]);
+    const asset: any = await registry.account.asset.fetch(assetPda);
+    const platformConfig: any = await registry.account.platformConfig.fetch(platformConfigPda);
+    const usdcMint: PublicKey = asset.usdcMint;
+
+    const tokenProgram = await getMintTokenProgram(connection, usdcMint);
+    const shareTokenProgram = await getMintTokenProgram(connection, mintPubkey);
+
     // Получаем associated token accounts
-    const buyerUsdcAccount = await getAssociatedTokenAddress(
-      new PublicKey('EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v'), // USDC mint на devnet
-      buyerPubkey
-    );
-    
-    const buyerSharesAccount = await getAssociatedTokenAddress(
-      mintPubkey,
-      buyerPubkey
+    const buyerUsdcAccount = getAssociatedTokenAddressSync(usdcMint, buyerPubkey, false, tokenProgram);
+    const buyerSharesAccount = getAssociatedTokenAddressSync(mintPubkey, buyerPubkey, false, shareTokenProgram);
+    const feeReceiverUsdcAccount = getAssociatedTokenAddressSync(
+      usdcMint,
+      platformConfig.feeReceiver,
+      true,
+      tokenProgram
     );
 
     // Создаем транзакцию
     const transaction = new Transaction();
-    
-    // Добавляем инструкцию для создания USDC ATA если нужно
-    try {
-      await connection.getAccountInfo(buyerUsdcAccount);
-    } catch {
+
+    // Добавляем инструкции для создания ATA если нужно
+    if (!(await connection.getAccountInfo(buyerUsdcAccount))) {
       transaction.add(
-        createAssociatedTokenAccountInstruction(
-          buyerPubkey,
-          buyerUsdcAccount,
-          buyerPubkey,
-          new PublicKey('EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v')
-        )
+        createAssociatedTokenAccountInstruction(buyerPubkey, buyerUsdcAccount, buyerPubkey, usdcMint, tokenProgram)
       );
     }
-    
-    // Проверяем существование ATA для shares
-    const sharesAccountInfo = await connection.getAccountInfo(buyerSharesAccount);
-    if (!sharesAccountInfo) {
+    if (!(await connection.getAccountInfo(buyerSharesAccount))) {
       transaction.add(
-        createAssociatedTokenAccountInstruction(
-          buyerPubkey,
-          buyerSharesAccount,
-          buyerPubkey,
-          mintPubkey
-        )
+        createAssociatedTokenAccountInstruction(buyerPubkey, buyerSharesAccount, buyerPubkey, mintPubkey, shareTokenProgram)
       );
     }
 
-    // Создаем инструкцию buy_shares
-    const instruction = {
-      keys: [
-        { pubkey: buyerPubkey, isSigner: true, isWritable: false },
-        { pubkey: assetPda, isSigner: false, isWritable: true },
-        { pubkey: mintPubkey, isSigner: false, isWritable: true },
-        { pubkey: buyerUsdcAccount, isSigner: false, isWritable: true },
-        { pubkey: buyerSharesAccount, isSigner: false, isWritable: true },
-        { pubkey: vaultUsdcPda, isSigner: false, isWritable: true },
-        { pubkey: mintAuthPda, isSigner: false, isWritable: false },
-        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-        { pubkey: REGISTRY_PROGRAM_ID, isSigner: false, isWritable: false },
-      ],
-      programId: MARKET_PROGRAM_ID,
-      data: Buffer.concat([
-        Buffer.from([175, 175, 109, 31, 13, 152, 155, 237]), // buy_shares discriminator
-        new BN(amount).toArrayLike(Buffer, 'le', 8)
-      ])
-    };
+    // max_total_usdc: стоимость по maxPrice плюс комиссии платформы и эмитента,
+    // которые программа добавляет сверху. Без maxPrice ограничение не задаем
+    let maxTotalUsdc = new BN('18446744073709551615');
+    if (maxPrice) {
+      const total = new BN(amount).mul(new BN(maxPrice));
+      maxTotalUsdc = total
+        .add(feeAmount(total, platformConfig.feeBps))
+        .add(feeAmount(total, issuerFeeBps(asset, platformConfig)));
+    }
+
+    // Credential опционален в программе (вместо него можно передать аттестацию),
+    // вместе с ним передается выдавший его провайдер
+    const credentialPda = findRegistryPda([Buffer.from('credential'), buyerPubkey.toBuffer()]);
+    const credential: any = await registry.account.credential.fetchNullable(credentialPda);
+
+    // Создаем инструкцию buy_shares по IDL программы
+    const instruction = await market.methods
+      .buyShares(new BN(amount), maxTotalUsdc, null, null, null)
+      .accountsStrict({
+        asset: assetPda,
+        assetMint: mintPubkey,
+        usdcMint,
+        platformConfig: platformConfigPda,
+        buyer: buyerPubkey,
+        denylistEntry: findRegistryPda([Buffer.from('denylist'), buyerPubkey.toBuffer()]),
+        credential: credential ? credentialPda : null,
+        credentialProvider: credential ? credential.provider : null,
+        kycProvider: null,
+        instructions: null,
+        acceptance: findRegistryPda([Buffer.from('acceptance'), assetPda.toBuffer(), buyerPubkey.toBuffer()]),
+        buyerUsdc: buyerUsdcAccount,
+        buyerShares: buyerSharesAccount,
+        feeReceiverUsdc: feeReceiverUsdcAccount,
+        position: findMarketPda([Buffer.from('position'), assetPda.toBuffer(), buyerPubkey.toBuffer()]),
+        holders: findMarketPda([Buffer.from('holders'), assetPda.toBuffer()]),
+        mintAuth: findRegistryPda([Buffer.from('mint_auth'), mintPubkey.toBuffer()]),
+        vaultUsdc: findRegistryPda([Buffer.from('vault_usdc'), mintPubkey.toBuffer()]),
+        marketAuthority: findMarketPda([Buffer.from('market_authority')]),
+        registryProgram: REGISTRY_PROGRAM_ID,
+        tokenProgram,
+        shareTokenProgram,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .instruction();
 
     transaction.add(instruction);
 
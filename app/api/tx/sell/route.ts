@@ -1,11 +1,23 @@
 import { NextRequest, NextResponse } from 'next/server';
-import { Connection, PublicKey, Transaction, SystemProgram, SYSVAR_RENT_PUBKEY } from '@solana/web3.js';
-import { AnchorProvider, Program, Wallet, BN } from '@coral-xyz/anchor';
-import { TOKEN_PROGRAM_ID, getAssociatedTokenAddress, createAssociatedTokenAccountInstruction } from '@solana/spl-token';
+import { Connection, PublicKey, Transaction, SystemProgram } from '@solana/web3.js';
+import { AnchorProvider, Wallet, BN } from '@coral-xyz/anchor';
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+  createAssociatedTokenAccountInstruction,
+} from '@solana/spl-token';
+import {
+  MARKET_PROGRAM_ID,
+  REGISTRY_PROGRAM_ID,
+  getPrograms,
+  findRegistryPda,
+  findMarketPda,
+  feeAmount,
+  issuerFeeBps,
+  getMintTokenProgram,
+} from '@/lib/rwa-programs';
 
 const RPC_URL = process.env.NEXT_PUBLIC_RPC_URL || 'https://api.devnet.solana.com';
-const MARKET_PROGRAM_ID = new PublicKey('Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnT');
-const REGISTRY_PROGRAM_ID = new PublicKey('3ER5UsBMKiP81Giq45LD6dL5adF8vH8v2juiBGT4NHSg');
 
 export async function POST(request: NextRequest) {
   try {
@@ -23,16 +35,16 @@ export async function POST(request: NextRequest) {
     }
 
     // Валидация типов данных
-    if (typeof amount !== 'number' || amount <= 0) {
+    if (!Number.isSafeInteger(amount) || amount <= 0) {
       return NextResponse.json(
-        { error: 'Amount must be a positive number' },
+        { error: 'Amount must be a positive integer in base units' },
         { status: 400 }
       );
     }
 
-    if (minPrice !== undefined && (typeof minPrice !== 'number' || minPrice <= 0)) {
+    if (minPrice !== undefined && (!Number.isSafeInteger(minPrice) || minPrice <= 0)) {
       return NextResponse.json(
-        { error: 'Min price must be a positive number' },
+        { error: 'Min price must be a positive integer in USDC base units' },
         { status: 400 }
       );
     }
@@ -72,77 +84,74 @@ export async function POST(request: NextRequest) {
     const mintPubkey = new PublicKey(mint);
     const sellerPubkey = new PublicKey(userWallet);
     
-    // Находим PDA для актива
-    const [assetPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from('asset'), mintPubkey.toBuffer()],
-      REGISTRY_PROGRAM_ID
-    );
-    
-    // Находим PDA для mint authority
-    const [mintAuthPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from('mint_auth'), mintPubkey.toBuffer()],
-      MARKET_PROGRAM_ID
-    );
-    
-    // Находим PDA для USDC vault
-    const [vaultUsdcPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from('vault_usdc'), mintPubkey.toBuffer()],
-      MARKET_PROGRAM_ID
-    );
-    
-    // Находим PDA для vault authority
-    const [vaultUsdcAuthPda] = PublicKey.findProgramAddressSync(
-      [Buffer.from('vault_usdc'), mintPubkey.toBuffer()],
-      MARKET_PROGRAM_ID
-    );
-    
+    const { market, registry } = getPrograms(provider);
+
+    // Находим PDA актива и читаем его вместе с конфигом платформы
+    const assetPda = findRegistryPda([Buffer.from('asset'), mintPubkey.toBuffer()]);
+    const platformConfigPda = findRegistryPda([Buffer.from('platform_config')]);
+    const asset: any = await registry.account.asset.fetch(assetPda);
+    const platformConfig: any = await registry.account.platformConfig.fetch(platformConfigPda);
+    const usdcMint: PublicKey = asset.usdcMint;
+
+    const tokenProgram = await getMintTokenProgram(connection, usdcMint);
+    const shareTokenProgram = await getMintTokenProgram(connection, mintPubkey);
+
     // Получаем associated token accounts
-    const sellerUsdcAccount = await getAssociatedTokenAddress(
-      new PublicKey('EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v'), // USDC mint на devnet
-      sellerPubkey
-    );
-    
-    const sellerSharesAccount = await getAssociatedTokenAddress(
-      mintPubkey,
-      sellerPubkey
+    const sellerUsdcAccount = getAssociatedTokenAddressSync(usdcMint, sellerPubkey, false, tokenProgram);
+    const sellerSharesAccount = getAssociatedTokenAddressSync(mintPubkey, sellerPubkey, false, shareTokenProgram);
+    const feeReceiverUsdcAccount = getAssociatedTokenAddressSync(
+      usdcMint,
+      platformConfig.feeReceiver,
+      true,
+      tokenProgram
     );
 
     // Создаем транзакцию
     const transaction = new Transaction();
-    
+
     // Добавляем инструкцию для создания USDC ATA если нужно
-    try {
-      await connection.getAccountInfo(sellerUsdcAccount);
-    } catch {
+    if (!(await connection.getAccountInfo(sellerUsdcAccount))) {
       transaction.add(
-        createAssociatedTokenAccountInstruction(
-          sellerPubkey,
-          sellerUsdcAccount,
-          sellerPubkey,
-          new PublicKey('EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v')
-        )
+        createAssociatedTokenAccountInstruction(sellerPubkey, sellerUsdcAccount, sellerPubkey, usdcMint, tokenProgram)
       );
     }
 
-    // Создаем инструкцию sell_shares
-    const instruction = {
-      keys: [
-        { pubkey: sellerPubkey, isSigner: true, isWritable: false },
-        { pubkey: assetPda, isSigner: false, isWritable: true },
-        { pubkey: mintPubkey, isSigner: false, isWritable: true },
-        { pubkey: sellerUsdcAccount, isSigner: false, isWritable: true },
-        { pubkey: sellerSharesAccount, isSigner: false, isWritable: true },
-        { pubkey: vaultUsdcPda, isSigner: false, isWritable: true },
-        { pubkey: mintAuthPda, isSigner: false, isWritable: false },
-        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-        { pubkey: REGISTRY_PROGRAM_ID, isSigner: false, isWritable: false },
-      ],
-      programId: MARKET_PROGRAM_ID,
-      data: Buffer.concat([
-        Buffer.from([51, 230, 133, 164, 1, 127, 131, 173]), // sell_shares discriminator
-        new BN(amount).toArrayLike(Buffer, 'le', 8)
-      ])
-    };
+    // min_total_usdc: программа сверяет выручку за вычетом комиссий платформы
+    // и эмитента, поэтому вычитаем их из стоимости по minPrice.
+    // Без minPrice ограничение не задаем
+    let minTotalUsdc = new BN(0);
+    if (minPrice) {
+      const total = new BN(amount).mul(new BN(minPrice));
+      minTotalUsdc = total
+        .sub(feeAmount(total, platformConfig.feeBps))
+        .sub(feeAmount(total, issuerFeeBps(asset, platformConfig)));
+    }
+
+    // Создаем инструкцию sell_shares по IDL программы
+    const instruction = await market.methods
+      .sellShares(new BN(amount), minTotalUsdc, null, null)
+      .accountsStrict({
+        asset: assetPda,
+        assetMint: mintPubkey,
+        usdcMint,
+        platformConfig: platformConfigPda,
+        seller: sellerPubkey,
+        denylistEntry: findRegistryPda([Buffer.from('denylist'), sellerPubkey.toBuffer()]),
+        sellerUsdc: sellerUsdcAccount,
+        sellerShares: sellerSharesAccount,
+        feeReceiverUsdc: feeReceiverUsdcAccount,
+        position: findMarketPda([Buffer.from('position'), assetPda.toBuffer(), sellerPubkey.toBuffer()]),
+        holders: findMarketPda([Buffer.from('holders'), assetPda.toBuffer()]),
+        vaultUsdc: findRegistryPda([Buffer.from('vault_usdc'), mintPubkey.toBuffer()]),
+        dividendReserve: findMarketPda([Buffer.from('dividend_reserve'), assetPda.toBuffer()]),
+        marketAuthority: findMarketPda([Buffer.from('market_authority')]),
+        registryProgram: REGISTRY_PROGRAM_ID,
+        tokenProgram,
+        shareTokenProgram,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .instruction();
 
     transaction.add(instruction);
 
@@ -1,14 +1,22 @@
 import { Connection, PublicKey, ParsedTransactionWithMeta, PartiallyDecodedInstruction } from '@solana/web3.js';
-import { Program, AnchorProvider, Wallet, BN } from '@coral-xyz/anchor';
+import { Program, AnchorProvider, Wallet, BN, EventParser, Idl } from '@coral-xyz/anchor';
+import { readFileSync } from 'fs';
+import path from 'path';
 import { database } from './database';
+import { AssetCreatedEvent, AssetMetadata, AssetUpdatedEvent } from './types/events';
 import dotenv from 'dotenv';
 
 dotenv.config();
 
+// Same IDL the app builds transactions from, resolved from both src/ and dist/.
+const REGISTRY_IDL_PATH =
+  process.env.RWA_REGISTRY_IDL_PATH || path.resolve(__dirname, '../../../lib/idl/rwa_registry.json');
+
 export class EventListener {
   private connection: Connection;
   private registryProgramId: PublicKey;
   private marketProgramId: PublicKey;
+  private registryEvents: EventParser;
 
   constructor() {
     this.connection = new Connection(
@@ -17,6 +25,14 @@ export class EventListener {
     );
     this.registryProgramId = new PublicKey(process.env.RWA_REGISTRY_PROGRAM_ID!);
     this.marketProgramId = new PublicKey(process.env.RWA_MARKET_PROGRAM_ID!);
+
+    // Program camelCases the IDL, so events decode as `assetCreated { assetMint, ... }`
+    const registryIdl: Idl = JSON.parse(readFileSync(REGISTRY_IDL_PATH, 'utf8'));
+    const registry = new Program(
+      { ...registryIdl, address: this.registryProgramId.toBase58() },
+      { connection: this.connection }
+    );
+    this.registryEvents = new EventParser(registry.programId, registry.coder);
   }
 
   async start(): Promise<void> {
@@ -41,22 +57,16 @@ export class EventListener {
 
   private async handleRegistryLogs(logs: any, context: any): Promise<void> {
     try {
-      const signature = logs.signature;
-      const transaction = await this.connection.getParsedTransaction(signature, {
-        commitment: 'confirmed',
-        maxSupportedTransactionVersion: 0
-      });
-
-      if (!transaction) return;
-
-      // Parse AssetCreated events
-      if (logs.logs.some((log: string) => log.includes('AssetCreated'))) {
-        await this.handleAssetCreated(transaction, signature);
-      }
-
-      // Parse AssetUpdated events
-      if (logs.logs.some((log: string) => log.includes('AssetUpdated'))) {
-        await this.handleAssetUpdated(transaction, signature);
+      // Decode the `Program data:` lines with the registry's event coder
+      for (const event of this.registryEvents.parseLogs(logs.logs)) {
+        switch (event.name) {
+          case 'assetCreated':
+            await this.handleAssetCreated(toAssetCreated(event.data));
+            break;
+          case 'assetUpdated':
+            await this.handleAssetUpdated(toAssetUpdated(event.data));
+            break;
+        }
       }
     } catch (error) {
       console.error('Error handling registry logs:', error);
@@ -102,49 +112,39 @@ export class EventListener {
     }
   }
 
-  private async handleAssetCreated(transaction: ParsedTransactionWithMeta, signature: string): Promise<void> {
+  private async handleAssetCreated(event: AssetCreatedEvent): Promise<void> {
     try {
-      // Extract asset creation data from transaction
-      const instruction = this.findProgramInstruction(transaction, this.registryProgramId);
-      if (!instruction) return;
-
-      // Parse accounts to extract asset data
-      const accounts = transaction.transaction.message.accountKeys;
-      
-      // This is a simplified version - in production, you'd decode the instruction data properly
-      const assetMint = accounts[2]?.pubkey.toString(); // Assuming asset mint is at index 2
-      const issuer = accounts[0]?.pubkey.toString(); // Assuming issuer is at index 0
-      const usdcMint = accounts[3]?.pubkey.toString(); // Assuming USDC mint is at index 3
-
-      if (assetMint && issuer && usdcMint) {
-        await database.query(`
-          INSERT INTO "Asset" (mint, issuer, "usdcMint", decimals, "priceUsdc", "totalSupply", "freeFloat", ticker)
-          VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-          ON CONFLICT (mint) DO NOTHING
-        `, [assetMint, issuer, usdcMint, 6, 1000000, 1000000, 1000000, 'DEMO']); // Default values for demo
+      const fields = {
+        issuer: event.issuer,
+        priceUsdc: BigInt(event.priceUsdc),
+        totalSupply: BigInt(event.totalSupply),
+        freeFloat: BigInt(event.freeFloat),
+        ...metadataFields(event.metadata),
+      };
+      await database.client.asset.upsert({
+        where: { mint: event.assetMint },
+        create: { mint: event.assetMint, ...fields },
+        update: fields,
+      });
 
-        console.log(`Asset created: ${assetMint}`);
-      }
+      console.log(`Asset created: ${event.assetMint} (${event.metadata.ticker})`);
     } catch (error) {
       console.error('Error handling AssetCreated event:', error);
     }
   }
 
-  private async handleAssetUpdated(transaction: ParsedTransactionWithMeta, signature: string): Promise<void> {
+  private async handleAssetUpdated(event: AssetUpdatedEvent): Promise<void> {
     try {
-      const instruction = this.findProgramInstruction(transaction, this.registryProgramId);
-      if (!instruction) return;
-
-      const accounts = transaction.transaction.message.accountKeys;
-      const assetMint = accounts[1]?.pubkey.toString(); // Assuming asset account is at index 1
-
-      if (assetMint) {
-        await database.query(`
-          UPDATE "Asset" SET "updatedAt" = CURRENT_TIMESTAMP WHERE mint = $1
-        `, [assetMint]);
+      await database.client.asset.updateMany({
+        where: { mint: event.assetMint },
+        data: {
+          priceUsdc: BigInt(event.priceUsdc),
+          freeFloat: BigInt(event.freeFloat),
+          ...metadataFields(event.metadata),
+        },
+      });
 
-        console.log(`Asset updated: ${assetMint}`);
-      }
+      console.log(`Asset updated: ${event.assetMint}`);
     } catch (error) {
       console.error('Error handling AssetUpdated event:', error);
     }
@@ -305,4 +305,46 @@ export class EventListener {
     
     return null;
   }
-}
\ No newline at end of file
+}
+
+function toMetadata(metadata: any): AssetMetadata {
+  return {
+    name: metadata.name,
+    ticker: metadata.ticker,
+    category: metadata.category,
+    country: Array.from(metadata.country),
+    uri: metadata.uri,
+  };
+}
+
+function toAssetCreated(data: any): AssetCreatedEvent {
+  return {
+    asset: data.asset.toBase58(),
+    issuer: data.issuer.toBase58(),
+    assetMint: data.assetMint.toBase58(),
+    priceUsdc: data.priceUsdc.toString(),
+    totalSupply: data.totalSupply.toString(),
+    freeFloat: data.freeFloat.toString(),
+    metadata: toMetadata(data.metadata),
+  };
+}
+
+function toAssetUpdated(data: any): AssetUpdatedEvent {
+  return {
+    asset: data.asset.toBase58(),
+    assetMint: data.assetMint.toBase58(),
+    priceUsdc: data.priceUsdc.toString(),
+    freeFloat: data.freeFloat.toString(),
+    metadata: toMetadata(data.metadata),
+  };
+}
+
+function metadataFields(metadata: AssetMetadata) {
+  return {
+    name: metadata.name,
+    ticker: metadata.ticker,
+    category: Object.keys(metadata.category)[0],
+    country: String.fromCharCode(...metadata.country),
+    uri: metadata.uri,
+  };
+}
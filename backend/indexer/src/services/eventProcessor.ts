@@ -30,7 +30,7 @@ export class EventProcessor {
   }
 
   private async processSharesBought(data: SharesBoughtEvent, signature: string, slot: number): Promise<void> {
-    const { buyer, mint, amount, totalPaid } = data;
+    const { buyer, mint, amount, totalPaid, platformFee, issuerFee } = data;
     
     // Create trade record
     await prisma.trade.create({
@@ -40,6 +40,7 @@ export class EventProcessor {
         mint,
         side: 'buy',
         priceUsdc: BigInt(totalPaid),
+        feeUsdc: BigInt(platformFee) + BigInt(issuerFee),
         amount: BigInt(amount),
         wallet: buyer,
       },
@@ -53,7 +54,7 @@ export class EventProcessor {
   }
 
   private async processSharesSold(data: SharesSoldEvent, signature: string, slot: number): Promise<void> {
-    const { seller, mint, amount, totalReceived } = data;
+    const { seller, mint, amount, totalReceived, platformFee, issuerFee } = data;
     
     // Create trade record
     await prisma.trade.create({
@@ -63,6 +64,7 @@ export class EventProcessor {
         mint,
         side: 'sell',
         priceUsdc: BigInt(totalReceived),
+        feeUsdc: BigInt(platformFee) + BigInt(issuerFee),
         amount: BigInt(amount),
         wallet: seller,
       },
@@ -114,6 +114,8 @@ export class SolanaListener {
             mint: 'mock_mint_pubkey',
             amount: '1000000',
             totalPaid: '1000000000',
+            platformFee: '0',
+            issuerFee: '0',
           }
         };
       }
@@ -127,6 +129,8 @@ export class SolanaListener {
             mint: 'mock_mint_pubkey',
             amount: '1000000',
             totalReceived: '1000000000',
+            platformFee: '0',
+            issuerFee: '0',
           }
         };
       }
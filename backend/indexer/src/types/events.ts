@@ -1,8 +1,41 @@
+export type AssetCategory =
+  | { realEstate: {} }
+  | { equipment: {} }
+  | { art: {} };
+
+export interface AssetMetadata {
+  name: string;
+  ticker: string;
+  category: AssetCategory;
+  country: number[];
+  uri: string;
+}
+
+export interface AssetCreatedEvent {
+  asset: string;
+  issuer: string;
+  assetMint: string;
+  priceUsdc: string;
+  totalSupply: string;
+  freeFloat: string;
+  metadata: AssetMetadata;
+}
+
+export interface AssetUpdatedEvent {
+  asset: string;
+  assetMint: string;
+  priceUsdc: string;
+  freeFloat: string;
+  metadata: AssetMetadata;
+}
+
 export interface SharesBoughtEvent {
   buyer: string;
   mint: string;
   amount: string;
   totalPaid: string;
+  platformFee: string;
+  issuerFee: string;
 }
 
 export interface SharesSoldEvent {
@@ -10,6 +43,8 @@ export interface SharesSoldEvent {
   mint: string;
   amount: string;
   totalReceived: string;
+  platformFee: string;
+  issuerFee: string;
 }
 
 export interface DividendOpenedEvent {
@@ -31,6 +66,8 @@ export interface DividendClosedEvent {
 }
 
 export type ProgramEvent = 
+  | { name: 'AssetCreated'; data: AssetCreatedEvent }
+  | { name: 'AssetUpdated'; data: AssetUpdatedEvent }
   | { name: 'SharesBought'; data: SharesBoughtEvent }
   | { name: 'SharesSold'; data: SharesSoldEvent }
   | { name: 'DividendOpened'; data: DividendOpenedEvent }
@@ -1,103 +1,334 @@
 #![allow(unexpected_cfgs)]
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount, Transfer};
-use rwa_registry::Asset;
+use anchor_spl::token_interface::{
+    self, spl_token_2022, Burn, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+};
+use rwa_registry::cpi::accounts::{ReleaseFloat, ReserveFloat, WithdrawVault};
+use rwa_registry::program::RwaRegistry;
+use rwa_registry::{
+    Asset, AssetRoles, Credential, KycAttestation, KycProvider, PlatformConfig, TermsAcceptance,
+};
+use solana_instructions_sysvar::{load_current_index_checked, load_instruction_at_checked};
+use solana_sdk_ids::{ed25519_program, sysvar::instructions as ix_sysvar};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnT");
 
+pub const MAX_PURCHASE_LOTS: usize = 8;
+/// Resting orders per side of an asset's order book.
+pub const MAX_BOOK_ORDERS: usize = 48;
+/// Matched fills the event queue holds until `consume_events` applies them.
+pub const MAX_QUEUE_EVENTS: usize = 32;
+pub const MAX_POOL_FEE_BPS: u16 = 1_000;
+/// LP tokens of the first deposit minted into the pool's `lp_lock` account
+/// and never redeemable, so the LP supply cannot be driven back to a few
+/// units and reinflated.
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
 #[program]
 pub mod rwa_market {
     use super::*;
 
-    pub fn buy_shares(ctx: Context<BuyShares>, amount: u64) -> Result<()> {
-        let asset = &mut ctx.accounts.asset;
+    /// Buys shares from free float. The buyer proves KYC either with a
+    /// `credential` account or, when `attestation` is set, with a provider
+    /// signature verified by the preceding ed25519 instruction. The platform
+    /// and issuer fees are charged on top of the price. Fails if the cost with
+    /// fees exceeds `max_total_usdc`, or if the quote the buyer saw
+    /// (`expected_price_usdc`, valid through `max_slot`) no longer holds.
+    pub fn buy_shares(
+        ctx: Context<BuyShares>,
+        amount: u64,
+        max_total_usdc: u64,
+        expected_price_usdc: Option<u64>,
+        max_slot: Option<u64>,
+        attestation: Option<KycAttestation>,
+    ) -> Result<()> {
+        let asset = &ctx.accounts.asset;
 
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            ctx.accounts.buyer.key(),
+            asset.key(),
+            MarketAction::Buy,
+        )?;
+        require!(asset.status.allows_buy(), RwaError::InvalidAssetStatus);
+        let country = check_buyer_kyc(ctx.accounts, attestation.as_ref())?;
+        require!(
+            asset.jurisdiction.allows(&country),
+            RwaError::JurisdictionNotAllowed
+        );
         require!(asset.free_float >= amount, RwaError::InsufficientFloat);
+        check_quote(asset.price_usdc, expected_price_usdc, max_slot)?;
+        let total = amount
+            .checked_mul(asset.price_usdc)
+            .ok_or(RwaError::MathOverflow)?;
+        let platform_fee = fee_amount(total, ctx.accounts.platform_config.fee_bps);
+        let issuer_fee = fee_amount(total, ctx.accounts.platform_config.issuer_fee_bps(asset));
+        let total_paid = total
+            .checked_add(platform_fee)
+            .and_then(|sum| sum.checked_add(issuer_fee))
+            .ok_or(RwaError::MathOverflow)?;
+        require!(total_paid <= max_total_usdc, RwaError::MaxTotalExceeded);
+
+        let balance_after = ctx
+            .accounts
+            .buyer_shares
+            .amount
+            .checked_add(amount)
+            .ok_or(RwaError::MathOverflow)?;
+        let holding = ctx.accounts.position.holding(balance_after)?;
+        require!(
+            asset.max_shares_per_wallet == 0 || holding <= asset.max_shares_per_wallet,
+            RwaError::WalletCapExceeded
+        );
+        let holders = &mut ctx.accounts.holders;
+        require!(
+            ctx.accounts.position.shares > 0
+                || asset.max_investors == 0
+                || holders.count < asset.max_investors,
+            RwaError::MaxInvestorsReached
+        );
+        holders.asset = asset.key();
+        holders.bump = ctx.bumps.holders;
+        let position = &mut ctx.accounts.position;
+        position.asset = asset.key();
+        position.holder = ctx.accounts.buyer.key();
+        position.bump = ctx.bumps.position;
+        position.sync(balance_after, holders)?;
+        if asset.lockup_secs > 0 {
+            let now = Clock::get()?.unix_timestamp;
+            let unlocks_at = now
+                .checked_add(asset.lockup_secs)
+                .ok_or(RwaError::MathOverflow)?;
+            position.add_lot(amount, unlocks_at, now)?;
+        }
 
-        // 1) списать USDC с покупателя в сейф
-        let total = amount.checked_mul(asset.price_usdc).unwrap();
+        // 1) списать USDC с покупателя в сейф, комиссия эмитента остается в сейфе
         let cpi_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.buyer_usdc.to_account_info(),
+                mint: ctx.accounts.usdc_mint.to_account_info(),
                 to: ctx.accounts.vault_usdc.to_account_info(),
                 authority: ctx.accounts.buyer.to_account_info(),
             },
         );
-        token::transfer(cpi_ctx, total)?;
-
-        // 2) заминтить доли покупателю от имени PDA
-        let asset_mint_key = ctx.accounts.asset_mint.key();
-        let seeds: &[&[u8]] = &[
-            b"mint_auth",
-            asset_mint_key.as_ref(),
-            &[asset.bump_mint_auth],
-        ];
-        let signer = &[&seeds[..]];
+        token_interface::transfer_checked(
+            cpi_ctx,
+            total_paid - platform_fee,
+            ctx.accounts.usdc_mint.decimals,
+        )?;
+
+        // комиссия платформы в казну
+        if platform_fee > 0 {
+            let cpi_fee = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.buyer_usdc.to_account_info(),
+                    mint: ctx.accounts.usdc_mint.to_account_info(),
+                    to: ctx.accounts.fee_receiver_usdc.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(
+                cpi_fee,
+                platform_fee,
+                ctx.accounts.usdc_mint.decimals,
+            )?;
+        }
+
+        // 2) зарезервировать float в реестре, реестр минтит доли покупателю
+        let seeds: &[&[u8]] = &[b"market_authority", &[ctx.bumps.market_authority]];
+        let signer = &[seeds];
         let cpi_ctx2 = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            MintTo {
-                mint: ctx.accounts.asset_mint.to_account_info(),
-                to: ctx.accounts.buyer_shares.to_account_info(),
-                authority: ctx.accounts.mint_auth.to_account_info(),
+            ctx.accounts.registry_program.to_account_info(),
+            ReserveFloat {
+                market_authority: ctx.accounts.market_authority.to_account_info(),
+                asset: ctx.accounts.asset.to_account_info(),
+                asset_mint: ctx.accounts.asset_mint.to_account_info(),
+                mint_authority: ctx.accounts.mint_auth.to_account_info(),
+                recipient: ctx.accounts.buyer_shares.to_account_info(),
+                token_program: ctx.accounts.share_token_program.to_account_info(),
             },
             signer,
         );
-        token::mint_to(cpi_ctx2, amount)?;
-
-        asset.free_float = asset.free_float.checked_sub(amount).unwrap();
+        rwa_registry::cpi::reserve_float(cpi_ctx2, amount)?;
 
         emit!(SharesBought {
             buyer: ctx.accounts.buyer.key(),
             mint: ctx.accounts.asset_mint.key(),
             amount,
-            total_paid: total,
+            total_paid,
+            platform_fee,
+            issuer_fee,
         });
         Ok(())
     }
 
-    pub fn sell_shares(ctx: Context<SellShares>, amount: u64) -> Result<()> {
-        let asset = &mut ctx.accounts.asset;
-        let total = amount.checked_mul(asset.price_usdc).unwrap();
+    /// Redeems shares into the vault at the asset price, less the platform and
+    /// issuer fees. Fails if the payout is below `min_total_usdc`, if the
+    /// quote the seller saw
+    /// (`expected_price_usdc`, valid through `max_slot`) no longer holds, or
+    /// if paying out would dip into USDC reserved for unclaimed dividends.
+    pub fn sell_shares(
+        ctx: Context<SellShares>,
+        amount: u64,
+        min_total_usdc: u64,
+        expected_price_usdc: Option<u64>,
+        max_slot: Option<u64>,
+    ) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            ctx.accounts.seller.key(),
+            asset.key(),
+            MarketAction::Sell,
+        )?;
+        require!(asset.status.allows_sell(), RwaError::InvalidAssetStatus);
+        check_quote(asset.price_usdc, expected_price_usdc, max_slot)?;
+        let total = amount
+            .checked_mul(asset.price_usdc)
+            .ok_or(RwaError::MathOverflow)?;
+        let platform_fee = fee_amount(total, ctx.accounts.platform_config.fee_bps);
+        let issuer_fee = fee_amount(total, ctx.accounts.platform_config.issuer_fee_bps(asset));
+        let total_received = total
+            .checked_sub(platform_fee)
+            .and_then(|rest| rest.checked_sub(issuer_fee))
+            .ok_or(RwaError::MathOverflow)?;
+        require!(total_received >= min_total_usdc, RwaError::MinTotalNotMet);
+        let payout = total_received
+            .checked_add(platform_fee)
+            .ok_or(RwaError::MathOverflow)?;
+        let reserved = reserved_dividends(&ctx.accounts.dividend_reserve)?;
+        require!(
+            payout <= ctx.accounts.vault_usdc.amount.saturating_sub(reserved),
+            RwaError::InsufficientProceeds
+        );
+        let balance_after = ctx
+            .accounts
+            .seller_shares
+            .amount
+            .checked_sub(amount)
+            .ok_or(RwaError::MathOverflow)?;
+        let locked = ctx
+            .accounts
+            .position
+            .locked_amount(Clock::get()?.unix_timestamp);
+        require!(balance_after >= locked, RwaError::SharesLocked);
 
         // 1) сжечь доли
         let cpi1 = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.share_token_program.to_account_info(),
             Burn {
                 mint: ctx.accounts.asset_mint.to_account_info(),
                 from: ctx.accounts.seller_shares.to_account_info(),
                 authority: ctx.accounts.seller.to_account_info(),
             },
         );
-        token::burn(cpi1, amount)?;
-
-        // 2) перевести USDC из сейфа продавцу (подписывает PDA сейфа)
-        let asset_mint_key = ctx.accounts.asset_mint.key();
-        let seeds: &[&[u8]] = &[
-            b"vault_usdc",
-            asset_mint_key.as_ref(),
-            &[asset.bump_vault_usdc],
-        ];
-        let signer = &[&seeds[..]];
+        token_interface::burn(cpi1, amount)?;
+
+        let holders = &mut ctx.accounts.holders;
+        holders.asset = asset.key();
+        holders.bump = ctx.bumps.holders;
+        let position = &mut ctx.accounts.position;
+        position.asset = asset.key();
+        position.holder = ctx.accounts.seller.key();
+        position.bump = ctx.bumps.position;
+        position.sync(balance_after, holders)?;
+
+        // 2) вернуть доли во float и перевести USDC из сейфа продавцу через реестр,
+        //    комиссия эмитента остается в сейфе
+        let seeds: &[&[u8]] = &[b"market_authority", &[ctx.bumps.market_authority]];
+        let signer = &[seeds];
         let cpi2 = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_usdc.to_account_info(),
-                to: ctx.accounts.seller_usdc.to_account_info(),
-                authority: ctx.accounts.vault_usdc_auth.to_account_info(),
+            ctx.accounts.registry_program.to_account_info(),
+            ReleaseFloat {
+                market_authority: ctx.accounts.market_authority.to_account_info(),
+                asset: ctx.accounts.asset.to_account_info(),
             },
             signer,
         );
-        token::transfer(cpi2, total)?;
-
-        asset.free_float = asset.free_float.checked_add(amount).unwrap();
+        rwa_registry::cpi::release_float(cpi2, amount)?;
+
+        let cpi3 = CpiContext::new_with_signer(
+            ctx.accounts.registry_program.to_account_info(),
+            WithdrawVault {
+                market_authority: ctx.accounts.market_authority.to_account_info(),
+                asset: ctx.accounts.asset.to_account_info(),
+                vault_usdc: ctx.accounts.vault_usdc.to_account_info(),
+                usdc_mint: ctx.accounts.usdc_mint.to_account_info(),
+                destination: ctx.accounts.seller_usdc.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            signer,
+        );
+        rwa_registry::cpi::withdraw_vault(cpi3, total_received)?;
+
+        // 3) комиссия платформы из сейфа в казну
+        if platform_fee > 0 {
+            let cpi4 = CpiContext::new_with_signer(
+                ctx.accounts.registry_program.to_account_info(),
+                WithdrawVault {
+                    market_authority: ctx.accounts.market_authority.to_account_info(),
+                    asset: ctx.accounts.asset.to_account_info(),
+                    vault_usdc: ctx.accounts.vault_usdc.to_account_info(),
+                    usdc_mint: ctx.accounts.usdc_mint.to_account_info(),
+                    destination: ctx.accounts.fee_receiver_usdc.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+                signer,
+            );
+            rwa_registry::cpi::withdraw_vault(cpi4, platform_fee)?;
+        }
 
         emit!(SharesSold {
             seller: ctx.accounts.seller.key(),
             mint: ctx.accounts.asset_mint.key(),
             amount,
-            total_received: total,
+            total_received,
+            platform_fee,
+            issuer_fee,
+        });
+        Ok(())
+    }
+
+    /// Treasurer (or the issuer if none is assigned) pulls sale proceeds out of
+    /// the vault. USDC reserved for unclaimed dividends stays in the vault.
+    pub fn withdraw_proceeds(ctx: Context<WithdrawProceeds>, amount: u64) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+
+        require!(
+            asset.status.allows_proceeds_withdrawal(),
+            RwaError::InvalidAssetStatus
+        );
+        let reserved = reserved_dividends(&ctx.accounts.dividend_reserve)?;
+        require!(
+            amount <= ctx.accounts.vault_usdc.amount.saturating_sub(reserved),
+            RwaError::InsufficientProceeds
+        );
+
+        let seeds: &[&[u8]] = &[b"market_authority", &[ctx.bumps.market_authority]];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.registry_program.to_account_info(),
+            WithdrawVault {
+                market_authority: ctx.accounts.market_authority.to_account_info(),
+                asset: asset.to_account_info(),
+                vault_usdc: ctx.accounts.vault_usdc.to_account_info(),
+                usdc_mint: ctx.accounts.usdc_mint.to_account_info(),
+                destination: ctx.accounts.destination.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            },
+            signer,
+        );
+        rwa_registry::cpi::withdraw_vault(cpi_ctx, amount)?;
+
+        emit!(ProceedsWithdrawn {
+            asset: asset.key(),
+            treasurer: ctx.accounts.treasurer.key(),
+            destination: ctx.accounts.destination.key(),
+            amount,
         });
         Ok(())
     }
@@ -111,6 +342,11 @@ pub mod rwa_market {
         let asset = &ctx.accounts.asset;
         let issuer = &ctx.accounts.issuer;
 
+        require!(
+            asset.status.allows_dividend_open(),
+            RwaError::InvalidAssetStatus
+        );
+
         // Get dividend key before mutable borrow
         let dividend_key = dividend.key();
 
@@ -121,16 +357,26 @@ pub mod rwa_market {
         dividend.supply_circ_at_open = supply_circ_at_open;
         dividend.created_at = Clock::get()?.unix_timestamp;
         dividend.is_closed = false;
+        dividend.claimed_amount = 0;
+
+        let reserve = &mut ctx.accounts.dividend_reserve;
+        reserve.asset = asset.key();
+        reserve.reserved = reserve
+            .reserved
+            .checked_add(total_amount)
+            .ok_or(RwaError::MathOverflow)?;
+        reserve.bump = ctx.bumps.dividend_reserve;
 
         // Transfer USDC from issuer to dividend vault
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from: ctx.accounts.issuer_usdc_ata.to_account_info(),
+            mint: ctx.accounts.usdc_mint.to_account_info(),
             to: ctx.accounts.vault_usdc.to_account_info(),
             authority: issuer.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, total_amount)?;
+        token_interface::transfer_checked(cpi_ctx, total_amount, ctx.accounts.usdc_mint.decimals)?;
 
         emit!(DividendOpened {
             dividend: dividend.key(),
@@ -143,12 +389,29 @@ pub mod rwa_market {
     }
 
     pub fn claim_dividend(ctx: Context<ClaimDividend>) -> Result<()> {
-        let dividend = &ctx.accounts.dividend;
+        let dividend = &mut ctx.accounts.dividend;
         let claim = &mut ctx.accounts.claim;
         let holder = &ctx.accounts.holder;
         let asset = &ctx.accounts.asset;
 
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            holder.key(),
+            asset.key(),
+            MarketAction::ClaimDividend,
+        )?;
+        require!(
+            asset.status.allows_dividend_claim(),
+            RwaError::InvalidAssetStatus
+        );
         require!(!dividend.is_closed, RwaError::DividendClosed);
+        require!(
+            ctx.accounts.credential.is_valid(
+                &ctx.accounts.credential_provider,
+                Clock::get()?.unix_timestamp
+            ),
+            RwaError::KycRequired
+        );
 
         // Calculate holder's share based on their balance
         let holder_balance = ctx.accounts.holder_shares_ata.amount;
@@ -157,6 +420,11 @@ pub mod rwa_market {
             .ok_or(RwaError::MathOverflow)?
             .checked_div(dividend.supply_circ_at_open as u128)
             .ok_or(RwaError::MathOverflow)? as u64;
+        // Never pay out more than is left of this dividend
+        let claim_amount = claim_amount.min(dividend.total_amount - dividend.claimed_amount);
+        dividend.claimed_amount += claim_amount;
+        let reserve = &mut ctx.accounts.dividend_reserve;
+        reserve.reserved = reserve.reserved.saturating_sub(claim_amount);
 
         // Initialize claim record
         claim.div = dividend.key();
@@ -164,21 +432,20 @@ pub mod rwa_market {
         claim.claimed_amount = claim_amount;
 
         // Transfer USDC from vault to holder
-        let vault_seeds = &[
-            b"vault_usdc",
-            asset.asset_mint.as_ref(),
-            &[asset.bump_vault_usdc],
-        ];
-        let signer = &[&vault_seeds[..]];
-
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.vault_usdc.to_account_info(),
-            to: ctx.accounts.holder_usdc_ata.to_account_info(),
-            authority: ctx.accounts.vault_usdc.to_account_info(),
+        let seeds: &[&[u8]] = &[b"market_authority", &[ctx.bumps.market_authority]];
+        let signer = &[seeds];
+
+        let cpi_accounts = WithdrawVault {
+            market_authority: ctx.accounts.market_authority.to_account_info(),
+            asset: asset.to_account_info(),
+            vault_usdc: ctx.accounts.vault_usdc.to_account_info(),
+            usdc_mint: ctx.accounts.usdc_mint.to_account_info(),
+            destination: ctx.accounts.holder_usdc_ata.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
         };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_program = ctx.accounts.registry_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        token::transfer(cpi_ctx, claim_amount)?;
+        rwa_registry::cpi::withdraw_vault(cpi_ctx, claim_amount)?;
 
         emit!(DividendClaimed {
             dividend: dividend.key(),
@@ -196,24 +463,25 @@ pub mod rwa_market {
 
         dividend.is_closed = true;
 
-        // Return remaining USDC to issuer
-        let remaining_balance = ctx.accounts.vault_usdc.amount;
+        // Return the unclaimed part of this dividend to the issuer
+        let remaining_balance = dividend.total_amount - dividend.claimed_amount;
+        let reserve = &mut ctx.accounts.dividend_reserve;
+        reserve.reserved = reserve.reserved.saturating_sub(remaining_balance);
         if remaining_balance > 0 {
-            let vault_seeds = &[
-                b"vault_usdc",
-                asset.asset_mint.as_ref(),
-                &[asset.bump_vault_usdc],
-            ];
-            let signer = &[&vault_seeds[..]];
-
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.vault_usdc.to_account_info(),
-                to: ctx.accounts.issuer_usdc_ata.to_account_info(),
-                authority: ctx.accounts.vault_usdc.to_account_info(),
+            let seeds: &[&[u8]] = &[b"market_authority", &[ctx.bumps.market_authority]];
+            let signer = &[seeds];
+
+            let cpi_accounts = WithdrawVault {
+                market_authority: ctx.accounts.market_authority.to_account_info(),
+                asset: asset.to_account_info(),
+                vault_usdc: ctx.accounts.vault_usdc.to_account_info(),
+                usdc_mint: ctx.accounts.usdc_mint.to_account_info(),
+                destination: ctx.accounts.issuer_usdc_ata.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
             };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_program = ctx.accounts.registry_program.to_account_info();
             let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-            token::transfer(cpi_ctx, remaining_balance)?;
+            rwa_registry::cpi::withdraw_vault(cpi_ctx, remaining_balance)?;
         }
 
         emit!(DividendClosed {
@@ -223,31 +491,954 @@ pub mod rwa_market {
 
         Ok(())
     }
+
+    /// Creates the asset's secondary order book and the escrows holding the
+    /// shares and USDC locked by resting orders. Matching also needs the fill
+    /// queue from `create_event_queue`.
+    pub fn create_order_book(ctx: Context<CreateOrderBook>) -> Result<()> {
+        let mut book = ctx.accounts.order_book.load_init()?;
+        book.asset = ctx.accounts.asset.key();
+        book.next_order_id = 1;
+        book.bump = ctx.bumps.order_book;
+        book.bump_share_escrow = ctx.bumps.book_shares;
+        book.bump_usdc_escrow = ctx.bumps.book_usdc;
+
+        emit!(OrderBookCreated {
+            asset: book.asset,
+            order_book: ctx.accounts.order_book.key(),
+        });
+        Ok(())
+    }
+
+    /// Creates the fill queue of an existing order book. Permissionless, the
+    /// payer only funds the rent.
+    pub fn create_event_queue(ctx: Context<CreateEventQueue>) -> Result<()> {
+        let mut queue = ctx.accounts.event_queue.load_init()?;
+        queue.asset = ctx.accounts.asset.key();
+        queue.bump = ctx.bumps.event_queue;
+
+        emit!(EventQueueCreated {
+            asset: queue.asset,
+            event_queue: ctx.accounts.event_queue.key(),
+        });
+        Ok(())
+    }
+
+    /// Places a limit order on the asset's order book. A bid locks
+    /// `price * quantity` USDC, an ask locks the shares; the order rests on
+    /// the book until `match_orders` fills it. The owner's own orders it would
+    /// cross are cancelled. Remaining accounts carry the share mint's transfer
+    /// hook accounts for asks.
+    pub fn place_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceOrder<'info>>,
+        side: Side,
+        price: u64,
+        quantity: u64,
+    ) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let owner = ctx.accounts.owner.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            owner,
+            asset.key(),
+            MarketAction::PlaceOrder,
+        )?;
+        require!(
+            asset.status.allows_secondary_trading(),
+            RwaError::InvalidAssetStatus
+        );
+        require!(price > 0 && quantity > 0, RwaError::InvalidOrder);
+        check_credential(
+            asset,
+            &ctx.accounts.credential,
+            &ctx.accounts.credential_provider,
+            now,
+        )?;
+
+        let holders = &mut ctx.accounts.holders;
+        holders.asset = asset.key();
+        holders.bump = ctx.bumps.holders;
+        let position = &mut ctx.accounts.position;
+        position.asset = asset.key();
+        position.holder = owner;
+        position.bump = ctx.bumps.position;
+        let open_orders = &mut ctx.accounts.open_orders;
+        open_orders.asset = asset.key();
+        open_orders.owner = owner;
+        open_orders.bump = ctx.bumps.open_orders;
+
+        position.set_book(open_orders)?;
+
+        let wallet = match side {
+            Side::Bid => {
+                check_terms_accepted(asset, ctx.accounts.acceptance.as_deref())?;
+                let holding = position
+                    .holding(ctx.accounts.owner_shares.amount)?
+                    .checked_add(quantity)
+                    .ok_or(RwaError::MathOverflow)?;
+                check_holder_limits(asset, position, holders, holding)?;
+
+                // 1) заблокировать USDC в эскроу стакана
+                let cost = quantity.checked_mul(price).ok_or(RwaError::MathOverflow)?;
+                let cpi_ctx = CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.owner_usdc.to_account_info(),
+                        mint: ctx.accounts.usdc_mint.to_account_info(),
+                        to: ctx.accounts.book_usdc.to_account_info(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                );
+                token_interface::transfer_checked(cpi_ctx, cost, ctx.accounts.usdc_mint.decimals)?;
+                ctx.accounts.owner_shares.amount
+            }
+            Side::Ask => {
+                let balance_after = ctx
+                    .accounts
+                    .owner_shares
+                    .amount
+                    .checked_sub(quantity)
+                    .ok_or(RwaError::MathOverflow)?;
+                require!(
+                    balance_after >= position.locked_amount(now),
+                    RwaError::SharesLocked
+                );
+
+                // 1) заблокировать доли в эскроу стакана
+                let cpi_ctx = CpiContext::new(
+                    ctx.accounts.share_token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.owner_shares.to_account_info(),
+                        mint: ctx.accounts.asset_mint.to_account_info(),
+                        to: ctx.accounts.book_shares.to_account_info(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+                transfer_shares(cpi_ctx, quantity, ctx.accounts.asset_mint.decimals)?;
+                balance_after
+            }
+        };
+        open_orders.lock(side, price, quantity)?;
+
+        // 2) снять свои встречные заявки, с которыми пересеклась бы новая
+        let mut book = ctx.accounts.order_book.load_mut()?;
+        for resting in book.orders_mut(side.opposite()).iter_mut() {
+            if resting.quantity > 0 && resting.owner == owner && side.crosses(price, resting.price)
+            {
+                open_orders.unlock(side.opposite(), resting.price, resting.quantity)?;
+                emit!(OrderCancelled {
+                    asset: asset.key(),
+                    order_id: resting.order_id,
+                    owner,
+                    side: side.opposite(),
+                    quantity: resting.quantity,
+                });
+                *resting = Order::default();
+            }
+        }
+        position.set_book(open_orders)?;
+        position.sync(wallet, holders)?;
+
+        // 3) выставить заявку в стакан, исполняет ее match_orders
+        let order_id = book.next_order_id;
+        book.next_order_id = order_id.checked_add(1).ok_or(RwaError::MathOverflow)?;
+        book.insert(
+            side,
+            Order {
+                order_id,
+                owner,
+                open_orders: open_orders.key(),
+                price,
+                quantity,
+            },
+        )?;
+
+        emit!(OrderPlaced {
+            asset: asset.key(),
+            order_id,
+            owner,
+            side,
+            price,
+            quantity,
+        });
+        Ok(())
+    }
+
+    /// Crank: matches crossing orders on the asset's book, best prices first,
+    /// and queues up to `limit` fills for `consume_events`. Each fill trades
+    /// at the price of the earlier order. Permissionless, any keeper may run it.
+    pub fn match_orders(ctx: Context<MatchOrders>, limit: u8) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+
+        require!(
+            asset.status.allows_secondary_trading(),
+            RwaError::InvalidAssetStatus
+        );
+
+        let mut book = ctx.accounts.order_book.load_mut()?;
+        let mut queue = ctx.accounts.event_queue.load_mut()?;
+        let mut fills = 0;
+        while fills < limit && !queue.is_full() {
+            let (Some(bid_index), Some(ask_index)) = (book.best(Side::Bid), book.best(Side::Ask))
+            else {
+                break;
+            };
+            let bid = book.bids[bid_index];
+            let ask = book.asks[ask_index];
+            if bid.price < ask.price {
+                break;
+            }
+
+            let (maker_order_id, taker_order_id, price) = if bid.order_id < ask.order_id {
+                (bid.order_id, ask.order_id, bid.price)
+            } else {
+                (ask.order_id, bid.order_id, ask.price)
+            };
+            let quantity = bid.quantity.min(ask.quantity);
+            queue.push(FillEvent {
+                maker_order_id,
+                taker_order_id,
+                buyer_open_orders: bid.open_orders,
+                seller_open_orders: ask.open_orders,
+                bid_price: bid.price,
+                price,
+                quantity,
+            })?;
+            book.bids[bid_index].reduce(quantity);
+            book.asks[ask_index].reduce(quantity);
+            fills += 1;
+
+            emit!(OrderFilled {
+                asset: asset.key(),
+                maker_order_id,
+                taker_order_id,
+                buyer: bid.owner,
+                seller: ask.owner,
+                price,
+                quantity,
+            });
+        }
+        Ok(())
+    }
+
+    /// Applies up to `limit` queued fills, oldest first, to the buyers' and
+    /// sellers' `OpenOrders` passed as remaining accounts. Stops at the first
+    /// fill whose accounts are missing. Permissionless.
+    pub fn consume_events<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ConsumeEvents<'info>>,
+        limit: u8,
+    ) -> Result<()> {
+        let mut queue = ctx.accounts.event_queue.load_mut()?;
+        let find = |key: &Pubkey| ctx.remaining_accounts.iter().find(|info| info.key == key);
+
+        let mut consumed = 0;
+        while consumed < limit {
+            let Some(fill) = queue.peek() else {
+                break;
+            };
+            let (Some(buyer_info), Some(seller_info)) = (
+                find(&fill.buyer_open_orders),
+                find(&fill.seller_open_orders),
+            ) else {
+                break;
+            };
+
+            let mut buyer = Account::<OpenOrders>::try_from(buyer_info)?;
+            buyer.fill_bid(fill.bid_price, fill.price, fill.quantity)?;
+            buyer.exit(&crate::ID)?;
+            let mut seller = Account::<OpenOrders>::try_from(seller_info)?;
+            seller.fill_ask(fill.price, fill.quantity)?;
+            seller.exit(&crate::ID)?;
+
+            queue.pop();
+            consumed += 1;
+        }
+
+        emit!(EventsConsumed {
+            asset: queue.asset,
+            consumed,
+            pending: queue.count,
+        });
+        Ok(())
+    }
+
+    /// Cancels a resting order; its locked funds become free for `settle_funds`.
+    pub fn cancel_order(ctx: Context<CancelOrder>, side: Side, order_id: u64) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let mut book = ctx.accounts.order_book.load_mut()?;
+        let index = book.find(side, order_id).ok_or(RwaError::OrderNotFound)?;
+        let order = book.orders(side)[index];
+        require_keys_eq!(order.owner, owner, RwaError::Unauthorized);
+
+        ctx.accounts
+            .open_orders
+            .unlock(side, order.price, order.quantity)?;
+        book.orders_mut(side)[index] = Order::default();
+        let position = &mut ctx.accounts.position;
+        position.set_book(&ctx.accounts.open_orders)?;
+        position.sync(ctx.accounts.owner_shares.amount, &mut ctx.accounts.holders)?;
+
+        emit!(OrderCancelled {
+            asset: ctx.accounts.asset.key(),
+            order_id,
+            owner,
+            side,
+            quantity: order.quantity,
+        });
+        Ok(())
+    }
+
+    /// Pays the owner's free order book balances out of the escrows. Remaining
+    /// accounts carry the share mint's transfer hook accounts.
+    pub fn settle_funds<'info>(ctx: Context<'_, '_, '_, 'info, SettleFunds<'info>>) -> Result<()> {
+        let asset_key = ctx.accounts.asset.key();
+        let owner = ctx.accounts.owner.key();
+
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            owner,
+            asset_key,
+            MarketAction::SettleFunds,
+        )?;
+
+        let open_orders = &mut ctx.accounts.open_orders;
+        let shares = open_orders.shares_free;
+        let usdc = open_orders.usdc_free;
+        open_orders.shares_free = 0;
+        open_orders.usdc_free = 0;
+
+        let bump = ctx.accounts.order_book.load()?.bump;
+        let seeds: &[&[u8]] = &[b"order_book", asset_key.as_ref(), &[bump]];
+        let signer = &[seeds];
+
+        // 1) выдать доли из эскроу стакана
+        if shares > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.share_token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.book_shares.to_account_info(),
+                    mint: ctx.accounts.asset_mint.to_account_info(),
+                    to: ctx.accounts.owner_shares.to_account_info(),
+                    authority: ctx.accounts.order_book.to_account_info(),
+                },
+                signer,
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+            transfer_shares(cpi_ctx, shares, ctx.accounts.asset_mint.decimals)?;
+        }
+
+        // 2) выдать USDC из эскроу стакана
+        if usdc > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.book_usdc.to_account_info(),
+                    mint: ctx.accounts.usdc_mint.to_account_info(),
+                    to: ctx.accounts.owner_usdc.to_account_info(),
+                    authority: ctx.accounts.order_book.to_account_info(),
+                },
+                signer,
+            );
+            token_interface::transfer_checked(cpi_ctx, usdc, ctx.accounts.usdc_mint.decimals)?;
+        }
+
+        ctx.accounts.owner_shares.reload()?;
+        let holders = &mut ctx.accounts.holders;
+        holders.asset = asset_key;
+        holders.bump = ctx.bumps.holders;
+        let position = &mut ctx.accounts.position;
+        position.asset = asset_key;
+        position.holder = owner;
+        position.bump = ctx.bumps.position;
+        position.set_book(&ctx.accounts.open_orders)?;
+        position.sync(ctx.accounts.owner_shares.amount, holders)?;
+
+        emit!(FundsSettled {
+            asset: asset_key,
+            owner,
+            shares,
+            usdc,
+        });
+        Ok(())
+    }
+
+    /// Creates the asset's constant-product pool: share and USDC reserves and
+    /// the LP mint. `fee_bps` of every swap input stays in the pool for the
+    /// liquidity providers.
+    pub fn create_pool(ctx: Context<CreatePool>, fee_bps: u16) -> Result<()> {
+        require!(fee_bps <= MAX_POOL_FEE_BPS, RwaError::InvalidPoolFee);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.asset = ctx.accounts.asset.key();
+        pool.lp_mint = ctx.accounts.lp_mint.key();
+        pool.fee_bps = fee_bps;
+        pool.bump = ctx.bumps.pool;
+        pool.bump_share_reserve = ctx.bumps.pool_shares;
+        pool.bump_usdc_reserve = ctx.bumps.pool_usdc;
+        pool.bump_lp_mint = ctx.bumps.lp_mint;
+        pool.bump_lp_lock = ctx.bumps.lp_lock;
+
+        emit!(PoolCreated {
+            asset: pool.asset,
+            pool: pool.key(),
+            lp_mint: pool.lp_mint,
+            fee_bps,
+        });
+        Ok(())
+    }
+
+    /// Adds liquidity at the pool ratio, taking at most `max_shares` and
+    /// `max_usdc`; the first deposit sets the price and locks
+    /// `MINIMUM_LIQUIDITY` of its LP tokens. Fails if fewer than `min_lp` LP
+    /// tokens would be minted. Remaining accounts carry the share mint's
+    /// transfer hook accounts.
+    pub fn deposit_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositLiquidity<'info>>,
+        max_shares: u64,
+        max_usdc: u64,
+        min_lp: u64,
+    ) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let owner = ctx.accounts.owner.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            owner,
+            asset.key(),
+            MarketAction::AddLiquidity,
+        )?;
+        require!(
+            asset.status.allows_secondary_trading(),
+            RwaError::InvalidAssetStatus
+        );
+        check_credential(
+            asset,
+            &ctx.accounts.credential,
+            &ctx.accounts.credential_provider,
+            now,
+        )?;
+
+        let reserve_shares = ctx.accounts.pool_shares.amount;
+        let reserve_usdc = ctx.accounts.pool_usdc.amount;
+        let supply = ctx.accounts.lp_mint.supply;
+        let (shares_in, usdc_in, lp_out) = if supply == 0 {
+            let lp = isqrt(max_shares as u128 * max_usdc as u128) as u64;
+            let lp = lp
+                .checked_sub(MINIMUM_LIQUIDITY)
+                .ok_or(RwaError::InsufficientLiquidity)?;
+            (max_shares, max_usdc, lp)
+        } else {
+            let lp_for_shares = mul_div(max_shares, supply, reserve_shares)?;
+            let lp = lp_for_shares.min(mul_div(max_usdc, supply, reserve_usdc)?);
+            (
+                mul_div_ceil(lp, reserve_shares, supply)?,
+                mul_div_ceil(lp, reserve_usdc, supply)?,
+                lp,
+            )
+        };
+        require!(lp_out > 0, RwaError::InsufficientLiquidity);
+        require!(lp_out >= min_lp, RwaError::SlippageExceeded);
+
+        let supply_after = if supply == 0 {
+            lp_out.checked_add(MINIMUM_LIQUIDITY)
+        } else {
+            supply.checked_add(lp_out)
+        }
+        .ok_or(RwaError::MathOverflow)?;
+        let position = &mut ctx.accounts.position;
+        position.asset = asset.key();
+        position.holder = owner;
+        position.bump = ctx.bumps.position;
+        position.in_pool = mul_div(
+            ctx.accounts
+                .owner_lp
+                .amount
+                .checked_add(lp_out)
+                .ok_or(RwaError::MathOverflow)?,
+            reserve_shares
+                .checked_add(shares_in)
+                .ok_or(RwaError::MathOverflow)?,
+            supply_after,
+        )?;
+        let balance_after = ctx
+            .accounts
+            .owner_shares
+            .amount
+            .checked_sub(shares_in)
+            .ok_or(RwaError::MathOverflow)?;
+        require!(
+            balance_after >= position.locked_amount(now),
+            RwaError::SharesLocked
+        );
+
+        // 1) внести доли и USDC в резервы пула
+        let cpi_shares = CpiContext::new(
+            ctx.accounts.share_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.owner_shares.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                to: ctx.accounts.pool_shares.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        transfer_shares(cpi_shares, shares_in, ctx.accounts.asset_mint.decimals)?;
+
+        let cpi_usdc = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.owner_usdc.to_account_info(),
+                mint: ctx.accounts.usdc_mint.to_account_info(),
+                to: ctx.accounts.pool_usdc.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        token_interface::transfer_checked(cpi_usdc, usdc_in, ctx.accounts.usdc_mint.decimals)?;
+
+        // 2) выпустить LP-токены, при первом взносе заблокировать MINIMUM_LIQUIDITY
+        let asset_key = asset.key();
+        let seeds: &[&[u8]] = &[b"pool", asset_key.as_ref(), &[ctx.accounts.pool.bump]];
+        let signer = &[seeds];
+        if supply == 0 {
+            let cpi_lock = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.lp_lock.to_account_info(),
+                    authority: ctx.accounts.pool.to_account_info(),
+                },
+                signer,
+            );
+            token_interface::mint_to(cpi_lock, MINIMUM_LIQUIDITY)?;
+        }
+        let cpi_lp = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: ctx.accounts.owner_lp.to_account_info(),
+                authority: ctx.accounts.pool.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::mint_to(cpi_lp, lp_out)?;
+
+        ctx.accounts.owner_shares.reload()?;
+        let holders = &mut ctx.accounts.holders;
+        holders.asset = asset_key;
+        holders.bump = ctx.bumps.holders;
+        ctx.accounts
+            .position
+            .sync(ctx.accounts.owner_shares.amount, holders)?;
+
+        emit!(LiquidityDeposited {
+            asset: asset_key,
+            owner,
+            shares: shares_in,
+            usdc: usdc_in,
+            lp: lp_out,
+        });
+        Ok(())
+    }
+
+    /// Burns `lp_amount` LP tokens for the matching part of both reserves.
+    /// Allowed in any asset or platform state, but LP tokens move freely, so
+    /// the owner receiving the shares must pass the same credential,
+    /// jurisdiction and holder-limit checks as a swap buyer. Remaining
+    /// accounts carry the share mint's transfer hook accounts.
+    pub fn withdraw_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawLiquidity<'info>>,
+        lp_amount: u64,
+        min_shares: u64,
+        min_usdc: u64,
+    ) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let owner = ctx.accounts.owner.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            owner,
+            asset.key(),
+            MarketAction::RemoveLiquidity,
+        )?;
+        check_credential(
+            asset,
+            &ctx.accounts.credential,
+            &ctx.accounts.credential_provider,
+            now,
+        )?;
+
+        let supply = ctx.accounts.lp_mint.supply;
+        let shares_out = mul_div(lp_amount, ctx.accounts.pool_shares.amount, supply)?;
+        let usdc_out = mul_div(lp_amount, ctx.accounts.pool_usdc.amount, supply)?;
+        require!(
+            shares_out >= min_shares && usdc_out >= min_usdc,
+            RwaError::SlippageExceeded
+        );
+
+        let holders = &mut ctx.accounts.holders;
+        holders.asset = asset.key();
+        holders.bump = ctx.bumps.holders;
+        let position = &mut ctx.accounts.position;
+        position.asset = asset.key();
+        position.holder = owner;
+        position.bump = ctx.bumps.position;
+        let lp_left = ctx
+            .accounts
+            .owner_lp
+            .amount
+            .checked_sub(lp_amount)
+            .ok_or(RwaError::MathOverflow)?;
+        position.in_pool = mul_div(
+            lp_left,
+            ctx.accounts.pool_shares.amount - shares_out,
+            supply - lp_amount,
+        )?;
+        let holding = position.holding(
+            ctx.accounts
+                .owner_shares
+                .amount
+                .checked_add(shares_out)
+                .ok_or(RwaError::MathOverflow)?,
+        )?;
+        check_holder_limits(asset, position, holders, holding)?;
+
+        // 1) сжечь LP-токены
+        let cpi_burn = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.owner_lp.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        token_interface::burn(cpi_burn, lp_amount)?;
+
+        // 2) выдать долю резервов
+        let asset_key = asset.key();
+        let seeds: &[&[u8]] = &[b"pool", asset_key.as_ref(), &[ctx.accounts.pool.bump]];
+        let signer = &[seeds];
+        let cpi_shares = CpiContext::new_with_signer(
+            ctx.accounts.share_token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.pool_shares.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                to: ctx.accounts.owner_shares.to_account_info(),
+                authority: ctx.accounts.pool.to_account_info(),
+            },
+            signer,
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        transfer_shares(cpi_shares, shares_out, ctx.accounts.asset_mint.decimals)?;
+
+        let cpi_usdc = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.pool_usdc.to_account_info(),
+                mint: ctx.accounts.usdc_mint.to_account_info(),
+                to: ctx.accounts.owner_usdc.to_account_info(),
+                authority: ctx.accounts.pool.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(cpi_usdc, usdc_out, ctx.accounts.usdc_mint.decimals)?;
+
+        ctx.accounts.owner_shares.reload()?;
+        ctx.accounts
+            .position
+            .sync(ctx.accounts.owner_shares.amount, &mut ctx.accounts.holders)?;
+
+        emit!(LiquidityWithdrawn {
+            asset: asset_key,
+            owner,
+            shares: shares_out,
+            usdc: usdc_out,
+            lp: lp_amount,
+        });
+        Ok(())
+    }
+
+    /// Swaps against the pool: `Side::Bid` pays `amount_in` USDC for shares,
+    /// `Side::Ask` sells `amount_in` shares for USDC. The pool fee is taken
+    /// from the input. Fails if less than `min_amount_out` comes back.
+    /// Remaining accounts carry the share mint's transfer hook accounts.
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        side: Side,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let owner = ctx.accounts.owner.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        check_not_denied(
+            &ctx.accounts.denylist_entry,
+            owner,
+            asset.key(),
+            MarketAction::Swap,
+        )?;
+        require!(
+            asset.status.allows_secondary_trading(),
+            RwaError::InvalidAssetStatus
+        );
+        check_credential(
+            asset,
+            &ctx.accounts.credential,
+            &ctx.accounts.credential_provider,
+            now,
+        )?;
+
+        let reserve_shares = ctx.accounts.pool_shares.amount;
+        let reserve_usdc = ctx.accounts.pool_usdc.amount;
+        let (reserve_in, reserve_out) = match side {
+            Side::Bid => (reserve_usdc, reserve_shares),
+            Side::Ask => (reserve_shares, reserve_usdc),
+        };
+        let (amount_out, fee) = swap_output(
+            reserve_in,
+            reserve_out,
+            amount_in,
+            ctx.accounts.pool.fee_bps,
+        )?;
+        require!(amount_out > 0, RwaError::InsufficientLiquidity);
+        require!(amount_out >= min_amount_out, RwaError::SlippageExceeded);
+
+        let holders = &mut ctx.accounts.holders;
+        holders.asset = asset.key();
+        holders.bump = ctx.bumps.holders;
+        let position = &mut ctx.accounts.position;
+        position.asset = asset.key();
+        position.holder = owner;
+        position.bump = ctx.bumps.position;
+
+        let asset_key = asset.key();
+        let seeds: &[&[u8]] = &[b"pool", asset_key.as_ref(), &[ctx.accounts.pool.bump]];
+        let signer = &[seeds];
+        match side {
+            Side::Bid => {
+                check_terms_accepted(asset, ctx.accounts.acceptance.as_deref())?;
+                let holding = ctx
+                    .accounts
+                    .owner_shares
+                    .amount
+                    .checked_add(amount_out)
+                    .ok_or(RwaError::MathOverflow)?;
+                check_holder_limits(asset, position, holders, holding)?;
+
+                // 1) USDC в пул, доли покупателю
+                let cpi_usdc = CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.owner_usdc.to_account_info(),
+                        mint: ctx.accounts.usdc_mint.to_account_info(),
+                        to: ctx.accounts.pool_usdc.to_account_info(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                );
+                token_interface::transfer_checked(
+                    cpi_usdc,
+                    amount_in,
+                    ctx.accounts.usdc_mint.decimals,
+                )?;
+
+                let cpi_shares = CpiContext::new_with_signer(
+                    ctx.accounts.share_token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.pool_shares.to_account_info(),
+                        mint: ctx.accounts.asset_mint.to_account_info(),
+                        to: ctx.accounts.owner_shares.to_account_info(),
+                        authority: ctx.accounts.pool.to_account_info(),
+                    },
+                    signer,
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+                transfer_shares(cpi_shares, amount_out, ctx.accounts.asset_mint.decimals)?;
+            }
+            Side::Ask => {
+                let balance_after = ctx
+                    .accounts
+                    .owner_shares
+                    .amount
+                    .checked_sub(amount_in)
+                    .ok_or(RwaError::MathOverflow)?;
+                require!(
+                    balance_after >= position.locked_amount(now),
+                    RwaError::SharesLocked
+                );
+
+                // 1) доли в пул, USDC продавцу
+                let cpi_shares = CpiContext::new(
+                    ctx.accounts.share_token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.owner_shares.to_account_info(),
+                        mint: ctx.accounts.asset_mint.to_account_info(),
+                        to: ctx.accounts.pool_shares.to_account_info(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+                transfer_shares(cpi_shares, amount_in, ctx.accounts.asset_mint.decimals)?;
+
+                let cpi_usdc = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.pool_usdc.to_account_info(),
+                        mint: ctx.accounts.usdc_mint.to_account_info(),
+                        to: ctx.accounts.owner_usdc.to_account_info(),
+                        authority: ctx.accounts.pool.to_account_info(),
+                    },
+                    signer,
+                );
+                token_interface::transfer_checked(
+                    cpi_usdc,
+                    amount_out,
+                    ctx.accounts.usdc_mint.decimals,
+                )?;
+            }
+        }
+
+        ctx.accounts.owner_shares.reload()?;
+        ctx.accounts
+            .position
+            .sync(ctx.accounts.owner_shares.amount, &mut ctx.accounts.holders)?;
+
+        emit!(Swapped {
+            asset: asset_key,
+            owner,
+            side,
+            amount_in,
+            amount_out,
+            fee,
+        });
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
 pub struct BuyShares<'info> {
     #[account(mut, has_one = asset_mint, has_one = usdc_mint)]
     pub asset: Account<'info, Asset>,
-    pub asset_mint: Account<'info, Mint>,
-    pub usdc_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = !platform_config.paused @ RwaError::PlatformPaused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
 
     #[account(mut)]
     pub buyer: Signer<'info>,
-    #[account(mut, associated_token::mint = usdc_mint, associated_token::authority = buyer)]
-    pub buyer_usdc: Account<'info, TokenAccount>,
-    #[account(mut, associated_token::mint = asset_mint, associated_token::authority = buyer)]
-    pub buyer_shares: Account<'info, TokenAccount>,
+    /// CHECK: registry denylist PDA of the buyer; must not exist
+    #[account(
+        seeds = [b"denylist", buyer.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+    /// Required unless an attestation is supplied
+    #[account(
+        seeds = [b"credential", buyer.key().as_ref()],
+        bump = credential.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub credential: Option<Account<'info, Credential>>,
+    /// Provider that issued `credential`, required with it
+    pub credential_provider: Option<Account<'info, KycProvider>>,
+    /// Signer of the attestation, required with it
+    #[account(
+        seeds = [b"kyc_provider", kyc_provider.authority.as_ref()],
+        bump = kyc_provider.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = kyc_provider.active @ RwaError::KycRequired
+    )]
+    pub kyc_provider: Option<Account<'info, KycProvider>>,
+    /// CHECK: instructions sysvar, required with an attestation
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// Buyer's acceptance of the current offering documents
+    #[account(
+        seeds = [b"acceptance", asset.key().as_ref(), buyer.key().as_ref()],
+        bump = acceptance.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = acceptance.documents_hash == asset.documents_hash @ RwaError::TermsNotAccepted
+    )]
+    pub acceptance: Account<'info, TermsAcceptance>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program
+    )]
+    pub buyer_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = buyer,
+        associated_token::token_program = share_token_program
+    )]
+    pub buyer_shares: InterfaceAccount<'info, TokenAccount>,
+    /// Platform treasury ATA receiving the platform fee
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = platform_config.fee_receiver,
+        associated_token::token_program = token_program
+    )]
+    pub fee_receiver_usdc: InterfaceAccount<'info, TokenAccount>,
 
-    /// PDA, владеющий правом mint
-    /// CHECK: signer by seeds
-    #[account(seeds = [b"mint_auth", asset_mint.key().as_ref()], bump = asset.bump_mint_auth)]
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", asset.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + AssetHolders::INIT_SPACE,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump
+    )]
+    pub holders: Account<'info, AssetHolders>,
+
+    /// PDA реестра, владеющий правом mint
+    /// CHECK: validated by seeds, signs inside rwa_registry
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump = asset.bump_mint_auth,
+        seeds::program = rwa_registry::ID
+    )]
     pub mint_auth: UncheckedAccount<'info>,
 
-    #[account(mut, token::mint = usdc_mint)]
-    pub vault_usdc: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"vault_usdc", asset_mint.key().as_ref()],
+        bump = asset.bump_vault_usdc,
+        seeds::program = rwa_registry::ID
+    )]
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    /// PDA рынка, авторизованный в реестре
+    /// CHECK: signer by seeds
+    #[account(seeds = [b"market_authority"], bump)]
+    pub market_authority: UncheckedAccount<'info>,
+
+    pub registry_program: Program<'info, RwaRegistry>,
+    /// Token program of the quote mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
 }
@@ -256,29 +1447,139 @@ pub struct BuyShares<'info> {
 pub struct SellShares<'info> {
     #[account(mut, has_one = asset_mint, has_one = usdc_mint)]
     pub asset: Account<'info, Asset>,
-    pub asset_mint: Account<'info, Mint>,
-    pub usdc_mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = !platform_config.paused @ RwaError::PlatformPaused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
 
     #[account(mut)]
     pub seller: Signer<'info>,
-    #[account(mut, associated_token::mint = usdc_mint, associated_token::authority = seller)]
-    pub seller_usdc: Account<'info, TokenAccount>,
-    #[account(mut, associated_token::mint = asset_mint, associated_token::authority = seller)]
-    pub seller_shares: Account<'info, TokenAccount>,
+    /// CHECK: registry denylist PDA of the seller; must not exist
+    #[account(
+        seeds = [b"denylist", seller.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program
+    )]
+    pub seller_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = seller,
+        associated_token::token_program = share_token_program
+    )]
+    pub seller_shares: InterfaceAccount<'info, TokenAccount>,
+    /// Platform treasury ATA receiving the platform fee
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = platform_config.fee_receiver,
+        associated_token::token_program = token_program
+    )]
+    pub fee_receiver_usdc: InterfaceAccount<'info, TokenAccount>,
 
-    /// PDA, владеющий vault_usdc
-    /// CHECK: signer by seeds
-    #[account(seeds = [b"vault_usdc", asset_mint.key().as_ref()], bump = asset.bump_vault_usdc)]
-    pub vault_usdc_auth: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", asset.key().as_ref(), seller.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = 8 + AssetHolders::INIT_SPACE,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump
+    )]
+    pub holders: Account<'info, AssetHolders>,
 
-    #[account(mut, token::mint = usdc_mint)]
-    pub vault_usdc: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"vault_usdc", asset_mint.key().as_ref()],
+        bump = asset.bump_vault_usdc,
+        seeds::program = rwa_registry::ID
+    )]
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: dividend reserve PDA of the asset; absent until the first dividend
+    #[account(seeds = [b"dividend_reserve", asset.key().as_ref()], bump)]
+    pub dividend_reserve: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// PDA рынка, авторизованный в реестре
+    /// CHECK: signer by seeds
+    #[account(seeds = [b"market_authority"], bump)]
+    pub market_authority: UncheckedAccount<'info>,
+
+    pub registry_program: Program<'info, RwaRegistry>,
+    /// Token program of the quote mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
 }
 
+#[derive(Accounts)]
+pub struct WithdrawProceeds<'info> {
+    #[account(
+        constraint = treasurer.key() == roles.treasury_authority(&asset) @ RwaError::Unauthorized
+    )]
+    pub treasurer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub roles: Account<'info, AssetRoles>,
+
+    #[account(address = asset.usdc_mint)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"vault_usdc", asset.asset_mint.as_ref()],
+        bump = asset.bump_vault_usdc,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = asset.usdc_mint)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: dividend reserve PDA of the asset; absent until the first dividend
+    #[account(seeds = [b"dividend_reserve", asset.key().as_ref()], bump)]
+    pub dividend_reserve: UncheckedAccount<'info>,
+
+    /// CHECK: signer by seeds, authorized in rwa_registry
+    #[account(seeds = [b"market_authority"], bump)]
+    pub market_authority: UncheckedAccount<'info>,
+
+    pub registry_program: Program<'info, RwaRegistry>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
 #[derive(Accounts)]
 #[instruction(total_amount: u64, supply_circ_at_open: u64)]
 pub struct OpenDividend<'info> {
@@ -286,8 +1587,9 @@ pub struct OpenDividend<'info> {
     pub issuer: Signer<'info>,
 
     #[account(
-        seeds = [b"asset", issuer.key().as_ref(), asset.asset_mint.as_ref()],
+        seeds = [b"asset", asset.asset_mint.as_ref()],
         bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
         has_one = issuer,
     )]
     pub asset: Account<'info, Asset>,
@@ -301,21 +1603,34 @@ pub struct OpenDividend<'info> {
     )]
     pub dividend: Account<'info, Dividend>,
 
+    #[account(
+        init_if_needed,
+        payer = issuer,
+        space = 8 + DividendReserve::INIT_SPACE,
+        seeds = [b"dividend_reserve", asset.key().as_ref()],
+        bump
+    )]
+    pub dividend_reserve: Account<'info, DividendReserve>,
+
     #[account(
         mut,
         token::mint = asset.usdc_mint,
         token::authority = issuer,
     )]
-    pub issuer_usdc_ata: Account<'info, TokenAccount>,
+    pub issuer_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = asset.usdc_mint)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         seeds = [b"vault_usdc", asset.asset_mint.as_ref()],
         bump = asset.bump_vault_usdc,
+        seeds::program = rwa_registry::ID,
     )]
-    pub vault_usdc: Account<'info, TokenAccount>,
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
@@ -324,19 +1639,44 @@ pub struct OpenDividend<'info> {
 pub struct ClaimDividend<'info> {
     #[account(mut)]
     pub holder: Signer<'info>,
-
+    /// CHECK: registry denylist PDA of the holder; must not exist
     #[account(
-        seeds = [b"asset", asset.issuer.as_ref(), asset.asset_mint.as_ref()],
-        bump = asset.bump_asset,
+        seeds = [b"denylist", holder.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"credential", holder.key().as_ref()],
+        bump = credential.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub credential: Account<'info, Credential>,
+    /// Provider that issued `credential`
+    pub credential_provider: Account<'info, KycProvider>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
     )]
     pub asset: Account<'info, Asset>,
 
     #[account(
+        mut,
         seeds = [b"div", asset.asset_mint.as_ref(), &dividend.created_at.to_le_bytes()],
         bump,
     )]
     pub dividend: Account<'info, Dividend>,
 
+    #[account(
+        mut,
+        seeds = [b"dividend_reserve", asset.key().as_ref()],
+        bump = dividend_reserve.bump,
+    )]
+    pub dividend_reserve: Account<'info, DividendReserve>,
+
     #[account(
         init,
         payer = holder,
@@ -350,23 +1690,32 @@ pub struct ClaimDividend<'info> {
         token::mint = asset.asset_mint,
         token::authority = holder,
     )]
-    pub holder_shares_ata: Account<'info, TokenAccount>,
+    pub holder_shares_ata: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         token::mint = asset.usdc_mint,
         token::authority = holder,
     )]
-    pub holder_usdc_ata: Account<'info, TokenAccount>,
+    pub holder_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = asset.usdc_mint)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         seeds = [b"vault_usdc", asset.asset_mint.as_ref()],
         bump = asset.bump_vault_usdc,
+        seeds::program = rwa_registry::ID,
     )]
-    pub vault_usdc: Account<'info, TokenAccount>,
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: signer by seeds, authorized in rwa_registry
+    #[account(seeds = [b"market_authority"], bump)]
+    pub market_authority: UncheckedAccount<'info>,
+
+    pub registry_program: Program<'info, RwaRegistry>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
@@ -377,8 +1726,9 @@ pub struct CloseDividend<'info> {
     pub issuer: Signer<'info>,
 
     #[account(
-        seeds = [b"asset", issuer.key().as_ref(), asset.asset_mint.as_ref()],
+        seeds = [b"asset", asset.asset_mint.as_ref()],
         bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
         has_one = issuer,
     )]
     pub asset: Account<'info, Asset>,
@@ -387,88 +1737,1877 @@ pub struct CloseDividend<'info> {
         mut,
         seeds = [b"div", asset.asset_mint.as_ref(), &dividend.created_at.to_le_bytes()],
         bump,
+        constraint = !dividend.is_closed @ RwaError::DividendClosed,
     )]
     pub dividend: Account<'info, Dividend>,
 
+    #[account(
+        mut,
+        seeds = [b"dividend_reserve", asset.key().as_ref()],
+        bump = dividend_reserve.bump,
+    )]
+    pub dividend_reserve: Account<'info, DividendReserve>,
+
     #[account(
         mut,
         token::mint = asset.usdc_mint,
         token::authority = issuer,
     )]
-    pub issuer_usdc_ata: Account<'info, TokenAccount>,
+    pub issuer_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = asset.usdc_mint)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         seeds = [b"vault_usdc", asset.asset_mint.as_ref()],
         bump = asset.bump_vault_usdc,
+        seeds::program = rwa_registry::ID,
     )]
-    pub vault_usdc: Account<'info, TokenAccount>,
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    /// CHECK: signer by seeds, authorized in rwa_registry
+    #[account(seeds = [b"market_authority"], bump)]
+    pub market_authority: UncheckedAccount<'info>,
+
+    pub registry_program: Program<'info, RwaRegistry>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Dividend {
-    pub asset_mint: Pubkey,           // 32 bytes
-    pub index: u64,                   // 8 bytes
-    pub total_amount: u64,            // 8 bytes
-    pub supply_circ_at_open: u64,     // 8 bytes
-    pub created_at: i64,              // 8 bytes
-    pub is_closed: bool,              // 1 byte
+#[derive(Accounts)]
+pub struct CreateOrderBook<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset_mint.key().as_ref()],
+        bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
+        has_one = issuer,
+        has_one = asset_mint,
+        has_one = usdc_mint,
+    )]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + std::mem::size_of::<OrderBook>(),
+        seeds = [b"order_book", asset.key().as_ref()],
+        bump
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+
+    /// Shares locked by resting asks
+    #[account(
+        init,
+        payer = issuer,
+        seeds = [b"book_shares", asset.key().as_ref()],
+        bump,
+        token::mint = asset_mint,
+        token::authority = order_book,
+        token::token_program = share_token_program
+    )]
+    pub book_shares: InterfaceAccount<'info, TokenAccount>,
+
+    /// USDC locked by resting bids
+    #[account(
+        init,
+        payer = issuer,
+        seeds = [b"book_usdc", asset.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = order_book,
+        token::token_program = token_program
+    )]
+    pub book_usdc: InterfaceAccount<'info, TokenAccount>,
+
+    /// Token program of the quote mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Claim {
-    pub div: Pubkey,                  // 32 bytes
-    pub holder: Pubkey,               // 32 bytes
-    pub claimed_amount: u64,          // 8 bytes
+#[derive(Accounts)]
+pub struct CreateEventQueue<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"order_book", asset.key().as_ref()],
+        bump = order_book.load()?.bump,
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<EventQueue>(),
+        seeds = [b"event_queue", asset.key().as_ref()],
+        bump
+    )]
+    pub event_queue: AccountLoader<'info, EventQueue>,
+
+    pub system_program: Program<'info, System>,
 }
 
-#[event]
-pub struct SharesBought {
-    pub buyer: Pubkey,
-    pub mint: Pubkey,
-    pub amount: u64,
-    pub total_paid: u64,
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    #[account(has_one = asset_mint, has_one = usdc_mint)]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = !platform_config.paused @ RwaError::PlatformPaused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: registry denylist PDA of the owner; must not exist
+    #[account(
+        seeds = [b"denylist", owner.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"credential", owner.key().as_ref()],
+        bump = credential.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub credential: Account<'info, Credential>,
+    /// Provider that issued `credential`
+    pub credential_provider: Account<'info, KycProvider>,
+    /// Owner's acceptance of the current offering documents, required for bids
+    #[account(
+        seeds = [b"acceptance", asset.key().as_ref(), owner.key().as_ref()],
+        bump = acceptance.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub acceptance: Option<Account<'info, TermsAcceptance>>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub owner_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = share_token_program
+    )]
+    pub owner_shares: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"order_book", asset.key().as_ref()],
+        bump = order_book.load()?.bump,
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + OpenOrders::INIT_SPACE,
+        seeds = [b"open_orders", asset.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+    #[account(
+        mut,
+        seeds = [b"book_shares", asset.key().as_ref()],
+        bump = order_book.load()?.bump_share_escrow,
+    )]
+    pub book_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"book_usdc", asset.key().as_ref()],
+        bump = order_book.load()?.bump_usdc_escrow,
+    )]
+    pub book_usdc: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", asset.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AssetHolders::INIT_SPACE,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump
+    )]
+    pub holders: Account<'info, AssetHolders>,
+
+    /// Token program of the quote mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
 }
 
-#[event]
-pub struct SharesSold {
-    pub seller: Pubkey,
-    pub mint: Pubkey,
-    pub amount: u64,
-    pub total_received: u64,
+#[derive(Accounts)]
+pub struct MatchOrders<'info> {
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = !platform_config.paused @ RwaError::PlatformPaused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"order_book", asset.key().as_ref()],
+        bump = order_book.load()?.bump,
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [b"event_queue", asset.key().as_ref()],
+        bump = event_queue.load()?.bump,
+    )]
+    pub event_queue: AccountLoader<'info, EventQueue>,
 }
 
-#[event]
-pub struct DividendOpened {
-    pub dividend: Pubkey,
-    pub asset: Pubkey,
-    pub total_amount: u64,
-    pub supply_circ_at_open: u64,
+#[derive(Accounts)]
+pub struct ConsumeEvents<'info> {
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        mut,
+        seeds = [b"event_queue", asset.key().as_ref()],
+        bump = event_queue.load()?.bump,
+    )]
+    pub event_queue: AccountLoader<'info, EventQueue>,
 }
 
-#[event]
-pub struct DividendClaimed {
-    pub dividend: Pubkey,
-    pub holder: Pubkey,
-    pub amount: u64,
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    pub owner: Signer<'info>,
+    #[account(has_one = asset_mint)]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        associated_token::mint = asset_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = share_token_program
+    )]
+    pub owner_shares: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"order_book", asset.key().as_ref()],
+        bump = order_book.load()?.bump,
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [b"open_orders", asset.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+
+    #[account(
+        mut,
+        seeds = [b"position", asset.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump = holders.bump,
+    )]
+    pub holders: Account<'info, AssetHolders>,
+
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
 }
 
-#[event]
-pub struct DividendClosed {
-    pub dividend: Pubkey,
-    pub remaining_amount: u64,
+#[derive(Accounts)]
+pub struct SettleFunds<'info> {
+    #[account(has_one = asset_mint, has_one = usdc_mint)]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: registry denylist PDA of the owner; must not exist
+    #[account(
+        seeds = [b"denylist", owner.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub owner_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = share_token_program
+    )]
+    pub owner_shares: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"order_book", asset.key().as_ref()],
+        bump = order_book.load()?.bump,
+    )]
+    pub order_book: AccountLoader<'info, OrderBook>,
+    #[account(
+        mut,
+        seeds = [b"open_orders", asset.key().as_ref(), owner.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Account<'info, OpenOrders>,
+    #[account(
+        mut,
+        seeds = [b"book_shares", asset.key().as_ref()],
+        bump = order_book.load()?.bump_share_escrow,
+    )]
+    pub book_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"book_usdc", asset.key().as_ref()],
+        bump = order_book.load()?.bump_usdc_escrow,
+    )]
+    pub book_usdc: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", asset.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AssetHolders::INIT_SPACE,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump
+    )]
+    pub holders: Account<'info, AssetHolders>,
+
+    /// Token program of the quote mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
-#[error_code]
-pub enum RwaError {
-    #[msg("Not enough free float")]
-    InsufficientFloat,
-    #[msg("Math overflow")]
-    MathOverflow,
-    #[msg("Dividend is closed")]
-    DividendClosed,
-}
\ No newline at end of file
+#[derive(Accounts)]
+pub struct CreatePool<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset_mint.key().as_ref()],
+        bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
+        has_one = issuer,
+        has_one = asset_mint,
+        has_one = usdc_mint,
+    )]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + Pool::INIT_SPACE,
+        seeds = [b"pool", asset.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        init,
+        payer = issuer,
+        seeds = [b"pool_shares", asset.key().as_ref()],
+        bump,
+        token::mint = asset_mint,
+        token::authority = pool,
+        token::token_program = share_token_program
+    )]
+    pub pool_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = issuer,
+        seeds = [b"pool_usdc", asset.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = pool,
+        token::token_program = token_program
+    )]
+    pub pool_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = issuer,
+        seeds = [b"lp_mint", asset.key().as_ref()],
+        bump,
+        mint::decimals = usdc_mint.decimals,
+        mint::authority = pool,
+        mint::token_program = token_program,
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    /// Holds the first deposit's `MINIMUM_LIQUIDITY`; nothing transfers out of it
+    #[account(
+        init,
+        payer = issuer,
+        seeds = [b"lp_lock", asset.key().as_ref()],
+        bump,
+        token::mint = lp_mint,
+        token::authority = pool,
+        token::token_program = token_program
+    )]
+    pub lp_lock: InterfaceAccount<'info, TokenAccount>,
+
+    /// Token program of the quote and LP mints
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositLiquidity<'info> {
+    #[account(has_one = asset_mint, has_one = usdc_mint)]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = !platform_config.paused @ RwaError::PlatformPaused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: registry denylist PDA of the owner; must not exist
+    #[account(
+        seeds = [b"denylist", owner.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"credential", owner.key().as_ref()],
+        bump = credential.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub credential: Account<'info, Credential>,
+    /// Provider that issued `credential`
+    pub credential_provider: Account<'info, KycProvider>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub owner_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = share_token_program
+    )]
+    pub owner_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = lp_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub owner_lp: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"pool", asset.key().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        mut,
+        seeds = [b"pool_shares", asset.key().as_ref()],
+        bump = pool.bump_share_reserve,
+    )]
+    pub pool_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"pool_usdc", asset.key().as_ref()],
+        bump = pool.bump_usdc_reserve,
+    )]
+    pub pool_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"lp_mint", asset.key().as_ref()],
+        bump = pool.bump_lp_mint,
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"lp_lock", asset.key().as_ref()],
+        bump = pool.bump_lp_lock,
+    )]
+    pub lp_lock: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", asset.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AssetHolders::INIT_SPACE,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump
+    )]
+    pub holders: Account<'info, AssetHolders>,
+
+    /// Token program of the quote and LP mints
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLiquidity<'info> {
+    #[account(has_one = asset_mint, has_one = usdc_mint)]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: registry denylist PDA of the owner; must not exist
+    #[account(
+        seeds = [b"denylist", owner.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"credential", owner.key().as_ref()],
+        bump = credential.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub credential: Account<'info, Credential>,
+    /// Provider that issued `credential`
+    pub credential_provider: Account<'info, KycProvider>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub owner_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = share_token_program
+    )]
+    pub owner_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub owner_lp: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"pool", asset.key().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        mut,
+        seeds = [b"pool_shares", asset.key().as_ref()],
+        bump = pool.bump_share_reserve,
+    )]
+    pub pool_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"pool_usdc", asset.key().as_ref()],
+        bump = pool.bump_usdc_reserve,
+    )]
+    pub pool_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"lp_mint", asset.key().as_ref()],
+        bump = pool.bump_lp_mint,
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", asset.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AssetHolders::INIT_SPACE,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump
+    )]
+    pub holders: Account<'info, AssetHolders>,
+
+    /// Token program of the quote and LP mints
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    #[account(has_one = asset_mint, has_one = usdc_mint)]
+    pub asset: Account<'info, Asset>,
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        seeds::program = rwa_registry::ID,
+        constraint = !platform_config.paused @ RwaError::PlatformPaused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: registry denylist PDA of the owner; must not exist
+    #[account(
+        seeds = [b"denylist", owner.key().as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub denylist_entry: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"credential", owner.key().as_ref()],
+        bump = credential.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub credential: Account<'info, Credential>,
+    /// Provider that issued `credential`
+    pub credential_provider: Account<'info, KycProvider>,
+    /// Owner's acceptance of the current offering documents, required to buy
+    #[account(
+        seeds = [b"acceptance", asset.key().as_ref(), owner.key().as_ref()],
+        bump = acceptance.bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub acceptance: Option<Account<'info, TermsAcceptance>>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub owner_usdc: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = asset_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = share_token_program
+    )]
+    pub owner_shares: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"pool", asset.key().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, Pool>,
+    #[account(
+        mut,
+        seeds = [b"pool_shares", asset.key().as_ref()],
+        bump = pool.bump_share_reserve,
+    )]
+    pub pool_shares: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"pool_usdc", asset.key().as_ref()],
+        bump = pool.bump_usdc_reserve,
+    )]
+    pub pool_usdc: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", asset.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AssetHolders::INIT_SPACE,
+        seeds = [b"holders", asset.key().as_ref()],
+        bump
+    )]
+    pub holders: Account<'info, AssetHolders>,
+
+    /// Token program of the quote and LP mints
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, anchor_spl::associated_token::AssociatedToken>,
+}
+
+/// Holder's shares as last seen by the market, in the wallet, on the order
+/// book and in the pool, plus the purchases still inside the asset's lockup.
+/// Seeds: `[b"position", asset, holder]`.
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub asset: Pubkey,                // 32 bytes
+    pub holder: Pubkey,               // 32 bytes
+    pub shares: u64,                  // 8 bytes, wallet + in_book + in_pool
+    pub in_book: u64,                 // 8 bytes, escrowed on the order book
+    pub in_pool: u64,                 // 8 bytes, pro-rata part of the pool reserve
+    #[max_len(MAX_PURCHASE_LOTS)]
+    pub lots: Vec<PurchaseLot>,       // 4 + 16 * 8 bytes
+    pub bump: u8,                     // 1 byte
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct PurchaseLot {
+    pub amount: u64,
+    pub unlocks_at: i64,
+}
+
+impl Position {
+    /// Shares from purchases that have not unlocked yet.
+    pub fn locked_amount(&self, now: i64) -> u64 {
+        self.lots
+            .iter()
+            .filter(|lot| lot.unlocks_at > now)
+            .map(|lot| lot.amount)
+            .sum()
+    }
+
+    /// Records a purchase locked until `unlocks_at` and drops unlocked lots.
+    /// With every slot taken the purchase joins the newest lot, which then
+    /// unlocks at the later of the two dates.
+    pub fn add_lot(&mut self, amount: u64, unlocks_at: i64, now: i64) -> Result<()> {
+        self.lots.retain(|lot| lot.unlocks_at > now);
+        if self.lots.len() < MAX_PURCHASE_LOTS {
+            self.lots.push(PurchaseLot { amount, unlocks_at });
+        } else if let Some(last) = self.lots.last_mut() {
+            last.amount = last
+                .amount
+                .checked_add(amount)
+                .ok_or(RwaError::MathOverflow)?;
+            last.unlocks_at = last.unlocks_at.max(unlocks_at);
+        }
+        Ok(())
+    }
+
+    /// Shares held with `wallet` in the wallet, counting those escrowed on
+    /// the order book and in the pool.
+    pub fn holding(&self, wallet: u64) -> Result<u64> {
+        wallet
+            .checked_add(self.in_book)
+            .and_then(|sum| sum.checked_add(self.in_pool))
+            .ok_or(error!(RwaError::MathOverflow))
+    }
+
+    /// Records the shares `open_orders` holds or still wants for the holder.
+    /// Fills only move bid shares to `shares_free` or take ask shares away,
+    /// so the figure stays an upper bound until the holder's next book call.
+    pub fn set_book(&mut self, open_orders: &OpenOrders) -> Result<()> {
+        self.in_book = open_orders
+            .shares_free
+            .checked_add(open_orders.shares_locked)
+            .and_then(|sum| sum.checked_add(open_orders.bid_quantity))
+            .ok_or(RwaError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Records the new wallet balance, entering or leaving the asset's holder
+    /// count when the holding opens or closes.
+    pub fn sync(&mut self, wallet: u64, holders: &mut AssetHolders) -> Result<()> {
+        let holding = self.holding(wallet)?;
+        match (self.shares > 0, holding > 0) {
+            (false, true) => {
+                holders.count = holders.count.checked_add(1).ok_or(RwaError::MathOverflow)?
+            }
+            (true, false) => holders.count = holders.count.saturating_sub(1),
+            _ => {}
+        }
+        self.shares = holding;
+        Ok(())
+    }
+}
+
+/// Number of wallets with an open position in the asset.
+#[account]
+#[derive(InitSpace)]
+pub struct AssetHolders {
+    pub asset: Pubkey,                // 32 bytes
+    pub count: u32,                   // 4 bytes
+    pub bump: u8,                     // 1 byte
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Dividend {
+    pub asset_mint: Pubkey,           // 32 bytes
+    pub index: u64,                   // 8 bytes
+    pub total_amount: u64,            // 8 bytes
+    pub supply_circ_at_open: u64,     // 8 bytes
+    pub created_at: i64,              // 8 bytes
+    pub is_closed: bool,              // 1 byte
+    pub claimed_amount: u64,          // 8 bytes
+}
+
+/// Vault USDC owed to holders across the asset's open dividends; proceeds
+/// withdrawals cannot touch it. Seeds: `[b"dividend_reserve", asset]`.
+#[account]
+#[derive(InitSpace)]
+pub struct DividendReserve {
+    pub asset: Pubkey,                // 32 bytes
+    pub reserved: u64,                // 8 bytes
+    pub bump: u8,                     // 1 byte
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Claim {
+    pub div: Pubkey,                  // 32 bytes
+    pub holder: Pubkey,               // 32 bytes
+    pub claimed_amount: u64,          // 8 bytes
+}
+
+/// Per-asset secondary order book. Bids and asks live in fixed slots, a slot
+/// with zero quantity is free. Seeds: `[b"order_book", asset]`.
+#[account(zero_copy)]
+pub struct OrderBook {
+    pub asset: Pubkey,                // 32 bytes
+    pub next_order_id: u64,           // 8 bytes
+    pub bids: [Order; MAX_BOOK_ORDERS], // 88 * 48 bytes
+    pub asks: [Order; MAX_BOOK_ORDERS], // 88 * 48 bytes
+    pub bump: u8,                     // 1 byte
+    pub bump_share_escrow: u8,        // 1 byte
+    pub bump_usdc_escrow: u8,         // 1 byte
+    pub _padding: [u8; 5],            // 5 bytes
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct Order {
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub open_orders: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+impl Order {
+    /// Takes a fill off the order, freeing its slot once nothing is left.
+    pub fn reduce(&mut self, quantity: u64) {
+        self.quantity -= quantity;
+        if self.quantity == 0 {
+            *self = Order::default();
+        }
+    }
+}
+
+impl OrderBook {
+    pub fn orders(&self, side: Side) -> &[Order; MAX_BOOK_ORDERS] {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    pub fn orders_mut(&mut self, side: Side) -> &mut [Order; MAX_BOOK_ORDERS] {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    /// Slot of the best resting order: highest bid or lowest ask, the oldest
+    /// one among equal prices.
+    pub fn best(&self, side: Side) -> Option<usize> {
+        self.orders(side)
+            .iter()
+            .enumerate()
+            .filter(|(_, order)| order.quantity > 0)
+            .min_by_key(|(_, order)| match side {
+                Side::Bid => (u64::MAX - order.price, order.order_id),
+                Side::Ask => (order.price, order.order_id),
+            })
+            .map(|(index, _)| index)
+    }
+
+    pub fn find(&self, side: Side, order_id: u64) -> Option<usize> {
+        self.orders(side)
+            .iter()
+            .position(|order| order.quantity > 0 && order.order_id == order_id)
+    }
+
+    pub fn insert(&mut self, side: Side, order: Order) -> Result<()> {
+        let slot = self
+            .orders_mut(side)
+            .iter_mut()
+            .find(|slot| slot.quantity == 0)
+            .ok_or(RwaError::OrderBookFull)?;
+        *slot = order;
+        Ok(())
+    }
+}
+
+/// Ring buffer of fills matched by `match_orders` and not yet applied to the
+/// traders' `OpenOrders`. Seeds: `[b"event_queue", asset]`.
+#[account(zero_copy)]
+pub struct EventQueue {
+    pub asset: Pubkey,                // 32 bytes
+    pub head: u64,                    // 8 bytes, slot of the oldest fill
+    pub count: u64,                   // 8 bytes
+    pub events: [FillEvent; MAX_QUEUE_EVENTS], // 104 * 32 bytes
+    pub bump: u8,                     // 1 byte
+    pub _padding: [u8; 7],            // 7 bytes
+}
+
+/// A matched trade. `bid_price` is what the buyer locked per share, `price`
+/// what they pay.
+#[zero_copy]
+#[derive(Default)]
+pub struct FillEvent {
+    pub maker_order_id: u64,
+    pub taker_order_id: u64,
+    pub buyer_open_orders: Pubkey,
+    pub seller_open_orders: Pubkey,
+    pub bid_price: u64,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+impl EventQueue {
+    pub fn is_full(&self) -> bool {
+        self.count as usize == MAX_QUEUE_EVENTS
+    }
+
+    pub fn push(&mut self, event: FillEvent) -> Result<()> {
+        require!(!self.is_full(), RwaError::EventQueueFull);
+        let tail = (self.head as usize + self.count as usize) % MAX_QUEUE_EVENTS;
+        self.events[tail] = event;
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn peek(&self) -> Option<FillEvent> {
+        (self.count > 0).then(|| self.events[self.head as usize])
+    }
+
+    pub fn pop(&mut self) {
+        if self.count > 0 {
+            self.head = (self.head + 1) % MAX_QUEUE_EVENTS as u64;
+            self.count -= 1;
+        }
+    }
+}
+
+/// Wallet's order book balances for an asset: funds locked by resting orders
+/// and fills waiting for `settle_funds`. Seeds: `[b"open_orders", asset, owner]`.
+#[account]
+#[derive(InitSpace)]
+pub struct OpenOrders {
+    pub asset: Pubkey,                // 32 bytes
+    pub owner: Pubkey,                // 32 bytes
+    pub shares_free: u64,             // 8 bytes
+    pub shares_locked: u64,           // 8 bytes
+    pub usdc_free: u64,               // 8 bytes
+    pub usdc_locked: u64,             // 8 bytes
+    pub bid_quantity: u64,            // 8 bytes, shares still wanted by bids
+    pub bump: u8,                     // 1 byte
+}
+
+impl OpenOrders {
+    /// Records the funds escrowed for a new order.
+    pub fn lock(&mut self, side: Side, price: u64, quantity: u64) -> Result<()> {
+        match side {
+            Side::Bid => {
+                let cost = quantity.checked_mul(price).ok_or(RwaError::MathOverflow)?;
+                self.usdc_locked = self
+                    .usdc_locked
+                    .checked_add(cost)
+                    .ok_or(RwaError::MathOverflow)?;
+                self.bid_quantity = self
+                    .bid_quantity
+                    .checked_add(quantity)
+                    .ok_or(RwaError::MathOverflow)?;
+            }
+            Side::Ask => {
+                self.shares_locked = self
+                    .shares_locked
+                    .checked_add(quantity)
+                    .ok_or(RwaError::MathOverflow)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Frees what a cancelled order still had locked.
+    pub fn unlock(&mut self, side: Side, price: u64, quantity: u64) -> Result<()> {
+        match side {
+            Side::Bid => {
+                let cost = quantity.checked_mul(price).ok_or(RwaError::MathOverflow)?;
+                self.usdc_locked = self
+                    .usdc_locked
+                    .checked_sub(cost)
+                    .ok_or(RwaError::MathOverflow)?;
+                self.usdc_free = self
+                    .usdc_free
+                    .checked_add(cost)
+                    .ok_or(RwaError::MathOverflow)?;
+                self.bid_quantity = self
+                    .bid_quantity
+                    .checked_sub(quantity)
+                    .ok_or(RwaError::MathOverflow)?;
+            }
+            Side::Ask => {
+                self.shares_locked = self
+                    .shares_locked
+                    .checked_sub(quantity)
+                    .ok_or(RwaError::MathOverflow)?;
+                self.shares_free = self
+                    .shares_free
+                    .checked_add(quantity)
+                    .ok_or(RwaError::MathOverflow)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Credits bought shares for a bid locked at `price` and filled at
+    /// `fill_price`; the price improvement is freed.
+    pub fn fill_bid(&mut self, price: u64, fill_price: u64, quantity: u64) -> Result<()> {
+        let locked = quantity.checked_mul(price).ok_or(RwaError::MathOverflow)?;
+        let paid = quantity
+            .checked_mul(fill_price)
+            .ok_or(RwaError::MathOverflow)?;
+        self.usdc_locked = self
+            .usdc_locked
+            .checked_sub(locked)
+            .ok_or(RwaError::MathOverflow)?;
+        self.usdc_free = self
+            .usdc_free
+            .checked_add(locked - paid)
+            .ok_or(RwaError::MathOverflow)?;
+        self.shares_free = self
+            .shares_free
+            .checked_add(quantity)
+            .ok_or(RwaError::MathOverflow)?;
+        self.bid_quantity = self
+            .bid_quantity
+            .checked_sub(quantity)
+            .ok_or(RwaError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Credits the USDC received for shares sold at `fill_price`.
+    pub fn fill_ask(&mut self, fill_price: u64, quantity: u64) -> Result<()> {
+        let received = quantity
+            .checked_mul(fill_price)
+            .ok_or(RwaError::MathOverflow)?;
+        self.shares_locked = self
+            .shares_locked
+            .checked_sub(quantity)
+            .ok_or(RwaError::MathOverflow)?;
+        self.usdc_free = self
+            .usdc_free
+            .checked_add(received)
+            .ok_or(RwaError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Constant-product share/USDC pool of an asset. The reserves are the
+/// balances of `pool_shares` and `pool_usdc`. Seeds: `[b"pool", asset]`.
+#[account]
+#[derive(InitSpace)]
+pub struct Pool {
+    pub asset: Pubkey,                // 32 bytes
+    pub lp_mint: Pubkey,              // 32 bytes
+    pub fee_bps: u16,                 // 2 bytes
+    pub bump: u8,                     // 1 byte
+    pub bump_share_reserve: u8,       // 1 byte
+    pub bump_usdc_reserve: u8,        // 1 byte
+    pub bump_lp_mint: u8,             // 1 byte
+    pub bump_lp_lock: u8,             // 1 byte
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+impl Side {
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        }
+    }
+
+    /// An incoming order at `price` trades with a resting order at `resting_price`.
+    pub fn crosses(self, price: u64, resting_price: u64) -> bool {
+        match self {
+            Side::Bid => price >= resting_price,
+            Side::Ask => price <= resting_price,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MarketAction {
+    Buy,
+    Sell,
+    ClaimDividend,
+    PlaceOrder,
+    SettleFunds,
+    AddLiquidity,
+    RemoveLiquidity,
+    Swap,
+}
+
+#[event]
+pub struct DeniedWalletRejected {
+    pub wallet: Pubkey,
+    pub asset: Pubkey,
+    pub action: MarketAction,
+}
+
+#[event]
+pub struct SharesBought {
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub total_paid: u64,
+    pub platform_fee: u64,
+    pub issuer_fee: u64,
+}
+
+#[event]
+pub struct SharesSold {
+    pub seller: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub total_received: u64,
+    pub platform_fee: u64,
+    pub issuer_fee: u64,
+}
+
+#[event]
+pub struct ProceedsWithdrawn {
+    pub asset: Pubkey,
+    pub treasurer: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DividendOpened {
+    pub dividend: Pubkey,
+    pub asset: Pubkey,
+    pub total_amount: u64,
+    pub supply_circ_at_open: u64,
+}
+
+#[event]
+pub struct DividendClaimed {
+    pub dividend: Pubkey,
+    pub holder: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DividendClosed {
+    pub dividend: Pubkey,
+    pub remaining_amount: u64,
+}
+
+#[event]
+pub struct OrderBookCreated {
+    pub asset: Pubkey,
+    pub order_book: Pubkey,
+}
+
+#[event]
+pub struct EventQueueCreated {
+    pub asset: Pubkey,
+    pub event_queue: Pubkey,
+}
+
+#[event]
+pub struct OrderPlaced {
+    pub asset: Pubkey,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub side: Side,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct OrderCancelled {
+    pub asset: Pubkey,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub side: Side,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct OrderFilled {
+    pub asset: Pubkey,
+    pub maker_order_id: u64,
+    pub taker_order_id: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+#[event]
+pub struct EventsConsumed {
+    pub asset: Pubkey,
+    pub consumed: u8,
+    pub pending: u64,
+}
+
+#[event]
+pub struct FundsSettled {
+    pub asset: Pubkey,
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub usdc: u64,
+}
+
+#[event]
+pub struct PoolCreated {
+    pub asset: Pubkey,
+    pub pool: Pubkey,
+    pub lp_mint: Pubkey,
+    pub fee_bps: u16,
+}
+
+#[event]
+pub struct LiquidityDeposited {
+    pub asset: Pubkey,
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub usdc: u64,
+    pub lp: u64,
+}
+
+#[event]
+pub struct LiquidityWithdrawn {
+    pub asset: Pubkey,
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub usdc: u64,
+    pub lp: u64,
+}
+
+#[event]
+pub struct Swapped {
+    pub asset: Pubkey,
+    pub owner: Pubkey,
+    pub side: Side,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+#[error_code]
+pub enum RwaError {
+    #[msg("Not enough free float")]
+    InsufficientFloat,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Dividend is closed")]
+    DividendClosed,
+    #[msg("Platform is paused")]
+    PlatformPaused,
+    #[msg("Operation not allowed in the current asset status")]
+    InvalidAssetStatus,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Wallet has no valid KYC credential")]
+    KycRequired,
+    #[msg("KYC attestation is invalid")]
+    InvalidAttestation,
+    #[msg("Buyer jurisdiction is not allowed for this asset")]
+    JurisdictionNotAllowed,
+    #[msg("Purchase exceeds the per-wallet holding cap")]
+    WalletCapExceeded,
+    #[msg("Asset has reached its maximum number of holders")]
+    MaxInvestorsReached,
+    #[msg("Wallet is on the sanctions denylist")]
+    WalletDenied,
+    #[msg("Shares are still inside the lockup period")]
+    SharesLocked,
+    #[msg("Buyer has not accepted the current offering documents")]
+    TermsNotAccepted,
+    #[msg("Purchase cost exceeds the buyer's maximum")]
+    MaxTotalExceeded,
+    #[msg("Sale proceeds are below the seller's minimum")]
+    MinTotalNotMet,
+    #[msg("Asset price differs from the expected price")]
+    PriceMismatch,
+    #[msg("Quote has expired")]
+    QuoteExpired,
+    #[msg("Order price and quantity must be positive")]
+    InvalidOrder,
+    #[msg("Order book side is full")]
+    OrderBookFull,
+    #[msg("Order not found")]
+    OrderNotFound,
+    #[msg("Event queue is full")]
+    EventQueueFull,
+    #[msg("Pool fee exceeds the maximum")]
+    InvalidPoolFee,
+    #[msg("Pool liquidity is insufficient for this operation")]
+    InsufficientLiquidity,
+    #[msg("Pool output is below the caller's minimum")]
+    SlippageExceeded,
+    #[msg("Withdrawal exceeds vault funds not reserved for dividends")]
+    InsufficientProceeds,
+}
+
+/// Fails if the registry holds a denylist entry for `wallet`. The rejection
+/// event stays in the failed transaction's logs for monitoring.
+fn check_not_denied(
+    entry: &AccountInfo,
+    wallet: Pubkey,
+    asset: Pubkey,
+    action: MarketAction,
+) -> Result<()> {
+    if entry.owner == &rwa_registry::ID && !entry.data_is_empty() {
+        emit!(DeniedWalletRejected {
+            wallet,
+            asset,
+            action,
+        });
+        return err!(RwaError::WalletDenied);
+    }
+    Ok(())
+}
+
+/// USDC held back for unclaimed dividends; zero before the first dividend.
+fn reserved_dividends(reserve: &AccountInfo) -> Result<u64> {
+    if reserve.data_is_empty() {
+        return Ok(0);
+    }
+    require_keys_eq!(*reserve.owner, crate::ID, RwaError::Unauthorized);
+    let data = reserve.try_borrow_data()?;
+    Ok(DividendReserve::try_deserialize(&mut &data[..])?.reserved)
+}
+
+/// Share `transfer_checked` that passes `remaining_accounts` on to Token-2022,
+/// which resolves the transfer hook's extra accounts from them.
+fn transfer_shares<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    spl_token_2022::onchain::invoke_transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+        &ctx.remaining_accounts,
+        amount,
+        decimals,
+        ctx.signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// `bps` basis points of `total`, rounded down.
+fn fee_amount(total: u64, bps: u16) -> u64 {
+    (total as u128 * bps as u128 / 10_000) as u64
+}
+
+/// `a * b / c` rounded down.
+fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+    let value = (a as u128)
+        .checked_mul(b as u128)
+        .and_then(|product| product.checked_div(c as u128))
+        .ok_or(RwaError::MathOverflow)?;
+    u64::try_from(value).map_err(|_| error!(RwaError::MathOverflow))
+}
+
+/// `a * b / c` rounded up.
+fn mul_div_ceil(a: u64, b: u64, c: u64) -> Result<u64> {
+    require!(c > 0, RwaError::MathOverflow);
+    let value = (a as u128 * b as u128).div_ceil(c as u128);
+    u64::try_from(value).map_err(|_| error!(RwaError::MathOverflow))
+}
+
+/// Constant-product output for `amount_in` against the reserves, after the
+/// pool fee; returns `(amount_out, fee)`.
+fn swap_output(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+    fee_bps: u16,
+) -> Result<(u64, u64)> {
+    let fee = fee_amount(amount_in, fee_bps);
+    let net_in = amount_in - fee;
+    let reserve_in_after = reserve_in
+        .checked_add(net_in)
+        .ok_or(RwaError::MathOverflow)?;
+    Ok((mul_div(reserve_out, net_in, reserve_in_after)?, fee))
+}
+
+/// Integer square root, rounded down.
+fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    let mut x = value;
+    let mut y = x / 2 + (x & 1);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+/// Fails if the asset price moved away from the caller's quote or the quote
+/// is past its last valid slot.
+fn check_quote(price: u64, expected_price: Option<u64>, max_slot: Option<u64>) -> Result<()> {
+    if let Some(expected_price) = expected_price {
+        require!(price == expected_price, RwaError::PriceMismatch);
+    }
+    if let Some(max_slot) = max_slot {
+        require!(Clock::get()?.slot <= max_slot, RwaError::QuoteExpired);
+    }
+    Ok(())
+}
+
+/// Fails unless `credential` is valid, issued by the still active `provider`,
+/// and from a country the asset allows.
+fn check_credential(
+    asset: &Asset,
+    credential: &Credential,
+    provider: &KycProvider,
+    now: i64,
+) -> Result<()> {
+    require!(credential.is_valid(provider, now), RwaError::KycRequired);
+    require!(
+        asset.jurisdiction.allows(&credential.country),
+        RwaError::JurisdictionNotAllowed
+    );
+    Ok(())
+}
+
+/// Fails unless `acceptance` covers the asset's current offering documents.
+fn check_terms_accepted(asset: &Asset, acceptance: Option<&TermsAcceptance>) -> Result<()> {
+    require!(
+        acceptance.is_some_and(|acceptance| acceptance.documents_hash == asset.documents_hash),
+        RwaError::TermsNotAccepted
+    );
+    Ok(())
+}
+
+/// Fails if holding `holding` shares breaks the per-wallet cap, or makes the
+/// wallet a new holder past the investor limit.
+fn check_holder_limits(
+    asset: &Asset,
+    position: &Position,
+    holders: &AssetHolders,
+    holding: u64,
+) -> Result<()> {
+    require!(
+        asset.max_shares_per_wallet == 0 || holding <= asset.max_shares_per_wallet,
+        RwaError::WalletCapExceeded
+    );
+    require!(
+        position.shares > 0 || asset.max_investors == 0 || holders.count < asset.max_investors,
+        RwaError::MaxInvestorsReached
+    );
+    Ok(())
+}
+
+/// Returns the buyer's KYC country from the attestation or stored credential.
+fn check_buyer_kyc(accounts: &BuyShares, attestation: Option<&KycAttestation>) -> Result<[u8; 2]> {
+    let now = Clock::get()?.unix_timestamp;
+
+    match attestation {
+        Some(attestation) => {
+            let provider = accounts
+                .kyc_provider
+                .as_ref()
+                .ok_or(RwaError::InvalidAttestation)?;
+            let instructions = accounts
+                .instructions
+                .as_ref()
+                .ok_or(RwaError::InvalidAttestation)?;
+            require!(
+                attestation.covers(&accounts.buyer.key(), &accounts.asset_mint.key(), now),
+                RwaError::InvalidAttestation
+            );
+            verify_ed25519_instruction(
+                instructions,
+                &provider.authority,
+                &attestation.signing_message()?,
+            )?;
+            Ok(attestation.country)
+        }
+        None => {
+            let credential = accounts.credential.as_ref().ok_or(RwaError::KycRequired)?;
+            let provider = accounts
+                .credential_provider
+                .as_ref()
+                .ok_or(RwaError::KycRequired)?;
+            require!(credential.is_valid(provider, now), RwaError::KycRequired);
+            Ok(credential.country)
+        }
+    }
+}
+
+/// Checks that the instruction right before the current one is an ed25519
+/// program instruction verifying exactly one signature of `message` by
+/// `signer`, with all data inline in that instruction.
+fn verify_ed25519_instruction(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, RwaError::InvalidAttestation);
+    let ix = load_instruction_at_checked((current - 1) as usize, instructions)?;
+    require_keys_eq!(
+        ix.program_id,
+        ed25519_program::ID,
+        RwaError::InvalidAttestation
+    );
+
+    // num_signatures (u8), padding (u8), then one Ed25519SignatureOffsets:
+    // signature offset/ix, pubkey offset/ix, message offset/size/ix (u16 each).
+    let data = &ix.data;
+    require!(
+        data.len() >= 16 && data[0] == 1,
+        RwaError::InvalidAttestation
+    );
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let pubkey_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    require!(
+        read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+        RwaError::InvalidAttestation
+    );
+
+    let signed_pubkey = data
+        .get(pubkey_offset..pubkey_offset + 32)
+        .ok_or(RwaError::InvalidAttestation)?;
+    let signed_message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(RwaError::InvalidAttestation)?;
+    require!(
+        signed_pubkey == signer.as_ref() && signed_message == message,
+        RwaError::InvalidAttestation
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_amount_rounds_down() {
+        assert_eq!(fee_amount(1_000_000, 50), 5_000);
+        assert_eq!(fee_amount(199, 50), 0);
+        assert_eq!(fee_amount(u64::MAX, 10_000), u64::MAX);
+        assert_eq!(fee_amount(1_000, 0), 0);
+    }
+
+    #[test]
+    fn mul_div_rounds_down_and_up() {
+        assert_eq!(mul_div(10, 10, 3).unwrap(), 33);
+        assert_eq!(mul_div_ceil(10, 10, 3).unwrap(), 34);
+        assert_eq!(mul_div_ceil(10, 9, 3).unwrap(), 30);
+        assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn mul_div_rejects_zero_divisor_and_overflow() {
+        assert!(mul_div(1, 1, 0).is_err());
+        assert!(mul_div_ceil(1, 1, 0).is_err());
+        assert!(mul_div(u64::MAX, 2, 1).is_err());
+        assert!(mul_div_ceil(u64::MAX, 2, 1).is_err());
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(3), 1);
+        assert_eq!(isqrt(4), 2);
+        assert_eq!(isqrt(99), 9);
+        assert_eq!(isqrt(1_000_000), 1_000);
+        assert_eq!(isqrt(u64::MAX as u128 * u64::MAX as u128), u64::MAX as u128);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn swap_output_takes_fee_from_input() {
+        // 1% fee: 1_000 in, 990 net against 100_000 / 100_000
+        let (amount_out, fee) = swap_output(100_000, 100_000, 1_000, 100).unwrap();
+        assert_eq!(fee, 10);
+        assert_eq!(amount_out, 980);
+    }
+
+    #[test]
+    fn swap_output_keeps_the_product() {
+        let (reserve_in, reserve_out) = (2_500_000u64, 40_000u64);
+        let (amount_out, fee) = swap_output(reserve_in, reserve_out, 75_000, 30).unwrap();
+        let k = reserve_in as u128 * reserve_out as u128;
+        let k_after = (reserve_in + 75_000 - fee) as u128 * (reserve_out - amount_out) as u128;
+        assert!(k_after >= k);
+        assert!(amount_out < reserve_out);
+    }
+
+    #[test]
+    fn swap_output_never_drains_the_pool() {
+        let (amount_out, _) = swap_output(1, 1_000, u64::MAX - 1, 0).unwrap();
+        assert_eq!(amount_out, 999);
+        assert!(swap_output(u64::MAX, 1, 1, 0).is_err());
+    }
+
+    fn position_with(lots: &[(u64, i64)]) -> Position {
+        Position {
+            asset: Pubkey::default(),
+            holder: Pubkey::default(),
+            shares: 0,
+            in_book: 0,
+            in_pool: 0,
+            lots: lots
+                .iter()
+                .map(|&(amount, unlocks_at)| PurchaseLot { amount, unlocks_at })
+                .collect(),
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn locked_amount_counts_only_pending_lots() {
+        let position = position_with(&[(100, 10), (50, 20), (25, 30)]);
+        assert_eq!(position.locked_amount(0), 175);
+        assert_eq!(position.locked_amount(10), 75);
+        assert_eq!(position.locked_amount(29), 25);
+        assert_eq!(position.locked_amount(30), 0);
+        assert_eq!(position_with(&[]).locked_amount(0), 0);
+    }
+
+    #[test]
+    fn add_lot_drops_unlocked_lots() {
+        let mut position = position_with(&[(100, 10), (50, 20)]);
+        position.add_lot(10, 40, 15).unwrap();
+        assert_eq!(position.lots.len(), 2);
+        assert_eq!(position.locked_amount(15), 60);
+    }
+
+    #[test]
+    fn add_lot_merges_into_the_newest_lot_when_full() {
+        let lots: Vec<(u64, i64)> = (0..MAX_PURCHASE_LOTS as i64)
+            .map(|i| (1, 100 + i))
+            .collect();
+        let mut position = position_with(&lots);
+        position.add_lot(5, 50, 0).unwrap();
+        assert_eq!(position.lots.len(), MAX_PURCHASE_LOTS);
+        let last = position.lots.last().unwrap();
+        assert_eq!(last.amount, 6);
+        assert_eq!(last.unlocks_at, 100 + MAX_PURCHASE_LOTS as i64 - 1);
+        assert_eq!(position.locked_amount(0), MAX_PURCHASE_LOTS as u64 + 5);
+    }
+
+    fn open_orders() -> OpenOrders {
+        OpenOrders {
+            asset: Pubkey::default(),
+            owner: Pubkey::default(),
+            shares_free: 0,
+            shares_locked: 0,
+            usdc_free: 0,
+            usdc_locked: 0,
+            bid_quantity: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn lock_and_unlock_move_funds_between_locked_and_free() {
+        let mut orders = open_orders();
+        orders.lock(Side::Bid, 100, 10).unwrap();
+        orders.lock(Side::Ask, 120, 7).unwrap();
+        assert_eq!(orders.usdc_locked, 1_000);
+        assert_eq!(orders.bid_quantity, 10);
+        assert_eq!(orders.shares_locked, 7);
+
+        orders.unlock(Side::Bid, 100, 4).unwrap();
+        orders.unlock(Side::Ask, 120, 7).unwrap();
+        assert_eq!(orders.usdc_locked, 600);
+        assert_eq!(orders.usdc_free, 400);
+        assert_eq!(orders.bid_quantity, 6);
+        assert_eq!(orders.shares_locked, 0);
+        assert_eq!(orders.shares_free, 7);
+    }
+
+    #[test]
+    fn unlock_rejects_more_than_was_locked() {
+        let mut orders = open_orders();
+        orders.lock(Side::Bid, 100, 1).unwrap();
+        assert!(orders.unlock(Side::Bid, 100, 2).is_err());
+        assert!(orders.unlock(Side::Ask, 100, 1).is_err());
+    }
+
+    #[test]
+    fn fills_conserve_escrowed_funds() {
+        let mut buyer = open_orders();
+        let mut seller = open_orders();
+        buyer.lock(Side::Bid, 100, 10).unwrap();
+        seller.lock(Side::Ask, 90, 10).unwrap();
+
+        // the resting ask sets the price, the buyer gets the improvement back
+        buyer.fill_bid(100, 90, 4).unwrap();
+        seller.fill_ask(90, 4).unwrap();
+        assert_eq!(buyer.usdc_locked, 600);
+        assert_eq!(buyer.usdc_free, 40);
+        assert_eq!(buyer.shares_free, 4);
+        assert_eq!(buyer.bid_quantity, 6);
+        assert_eq!(seller.shares_locked, 6);
+        assert_eq!(seller.usdc_free, 360);
+
+        buyer.unlock(Side::Bid, 100, 6).unwrap();
+        seller.unlock(Side::Ask, 90, 6).unwrap();
+        assert_eq!(buyer.usdc_locked + seller.usdc_locked, 0);
+        assert_eq!(buyer.usdc_free + seller.usdc_free, 1_000);
+        assert_eq!(buyer.shares_free + seller.shares_free, 10);
+        assert_eq!(buyer.bid_quantity, 0);
+    }
+
+    #[test]
+    fn fill_rejects_more_than_was_locked() {
+        let mut orders = open_orders();
+        orders.lock(Side::Bid, 100, 1).unwrap();
+        orders.lock(Side::Ask, 100, 1).unwrap();
+        assert!(orders.fill_bid(100, 100, 2).is_err());
+        assert!(orders.fill_ask(100, 2).is_err());
+    }
+
+    #[test]
+    fn escrowed_shares_keep_the_holder_counted() {
+        let mut holders = AssetHolders {
+            asset: Pubkey::default(),
+            count: 0,
+            bump: 0,
+        };
+        let mut position = position_with(&[]);
+        position.sync(10, &mut holders).unwrap();
+        assert_eq!(holders.count, 1);
+
+        // the whole wallet goes into an ask and the pool
+        let mut orders = open_orders();
+        orders.lock(Side::Ask, 100, 6).unwrap();
+        position.set_book(&orders).unwrap();
+        position.in_pool = 4;
+        position.sync(0, &mut holders).unwrap();
+        assert_eq!(position.shares, 10);
+        assert_eq!(holders.count, 1);
+
+        // a pending bid counts towards the wallet cap too
+        orders.lock(Side::Bid, 100, 5).unwrap();
+        position.set_book(&orders).unwrap();
+        assert_eq!(position.holding(3).unwrap(), 18);
+
+        orders.unlock(Side::Ask, 100, 6).unwrap();
+        orders.unlock(Side::Bid, 100, 5).unwrap();
+        orders.shares_free = 0;
+        position.set_book(&orders).unwrap();
+        position.in_pool = 0;
+        position.sync(0, &mut holders).unwrap();
+        assert_eq!(holders.count, 0);
+    }
+
+    #[test]
+    fn event_queue_is_fifo_across_the_wrap() {
+        let mut queue = EventQueue {
+            asset: Pubkey::default(),
+            head: MAX_QUEUE_EVENTS as u64 - 1,
+            count: 0,
+            events: [FillEvent::default(); MAX_QUEUE_EVENTS],
+            bump: 0,
+            _padding: [0; 7],
+        };
+        let fill = |quantity| FillEvent {
+            quantity,
+            ..FillEvent::default()
+        };
+        assert!(queue.peek().is_none());
+        for quantity in 1..=MAX_QUEUE_EVENTS as u64 {
+            queue.push(fill(quantity)).unwrap();
+        }
+        assert!(queue.is_full());
+        assert!(queue.push(fill(0)).is_err());
+
+        for quantity in 1..=MAX_QUEUE_EVENTS as u64 {
+            assert_eq!(queue.peek().unwrap().quantity, quantity);
+            queue.pop();
+        }
+        assert!(queue.peek().is_none());
+        queue.pop();
+        assert_eq!(queue.count, 0);
+    }
+}
@@ -1,26 +1,273 @@
 #![allow(unexpected_cfgs)]
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::metadata::{
+    self, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3, Metadata,
+    UpdateMetadataAccountsV2,
+};
+use anchor_spl::token_interface::spl_token_2022::extension::ExtensionType;
+use anchor_spl::token_interface::{
+    self, get_mint_extension_data, spl_token_2022, Burn, FreezeAccount, Mint, MintTo, ThawAccount,
+    Token2022, TokenAccount, TokenInterface, TransferChecked,
+};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
+/// rwa_market program; its `market_authority` PDA is the only signer
+/// allowed to move float and vault funds through CPI.
+pub const MARKET_PROGRAM_ID: Pubkey = pubkey!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnT");
+
+/// rwa_transfer_hook program, set as the transfer hook of Token-2022 share mints.
+pub const TRANSFER_HOOK_PROGRAM_ID: Pubkey =
+    pubkey!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnU");
+
+pub const MAX_QUOTE_MINTS: usize = 8;
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+// Bounds match the Metaplex token metadata limits for name/symbol/uri.
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_TICKER_LEN: usize = 10;
+pub const MAX_URI_LEN: usize = 200;
+
+pub const MAX_JURISDICTIONS: usize = 16;
+pub const MAX_DISABLED_KYC_PROVIDERS: usize = 32;
+
 #[program]
 pub mod rwa_registry {
     use super::*;
 
+    /// Creates the global config. Only the program upgrade authority may call it.
+    /// `max_issuer_fee_bps` caps every asset's issuer fee.
+    pub fn initialize_platform(
+        ctx: Context<InitializePlatform>,
+        allowed_quote_mints: Vec<Pubkey>,
+        fee_bps: u16,
+        max_issuer_fee_bps: u16,
+        fee_receiver: Pubkey,
+    ) -> Result<()> {
+        require!(
+            allowed_quote_mints.len() <= MAX_QUOTE_MINTS,
+            ErrorCode::TooManyQuoteMints
+        );
+        check_fee_sum(fee_bps, max_issuer_fee_bps)?;
+
+        let config = &mut ctx.accounts.platform_config;
+        config.admin = ctx.accounts.admin.key();
+        config.allowed_quote_mints = allowed_quote_mints;
+        config.fee_bps = fee_bps;
+        config.max_issuer_fee_bps = max_issuer_fee_bps;
+        config.fee_receiver = fee_receiver;
+        config.paused = false;
+        config.bump = ctx.bumps.platform_config;
+
+        emit!(PlatformConfigUpdated {
+            admin: config.admin,
+            allowed_quote_mints: config.allowed_quote_mints.clone(),
+            fee_bps: config.fee_bps,
+            max_issuer_fee_bps: config.max_issuer_fee_bps,
+            fee_receiver: config.fee_receiver,
+            paused: config.paused,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_platform(
+        ctx: Context<UpdatePlatform>,
+        new_admin: Option<Pubkey>,
+        new_allowed_quote_mints: Option<Vec<Pubkey>>,
+        new_fee_bps: Option<u16>,
+        new_max_issuer_fee_bps: Option<u16>,
+        new_fee_receiver: Option<Pubkey>,
+        new_paused: Option<bool>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.platform_config;
+
+        if let Some(admin) = new_admin {
+            config.admin = admin;
+        }
+
+        if let Some(mints) = new_allowed_quote_mints {
+            require!(mints.len() <= MAX_QUOTE_MINTS, ErrorCode::TooManyQuoteMints);
+            config.allowed_quote_mints = mints;
+        }
+
+        if new_fee_bps.is_some() || new_max_issuer_fee_bps.is_some() {
+            let fee_bps = new_fee_bps.unwrap_or(config.fee_bps);
+            let max_issuer_fee_bps = new_max_issuer_fee_bps.unwrap_or(config.max_issuer_fee_bps);
+            check_fee_sum(fee_bps, max_issuer_fee_bps)?;
+            config.fee_bps = fee_bps;
+            config.max_issuer_fee_bps = max_issuer_fee_bps;
+        }
+
+        if let Some(fee_receiver) = new_fee_receiver {
+            config.fee_receiver = fee_receiver;
+        }
+
+        if let Some(paused) = new_paused {
+            config.paused = paused;
+        }
+
+        emit!(PlatformConfigUpdated {
+            admin: config.admin,
+            allowed_quote_mints: config.allowed_quote_mints.clone(),
+            fee_bps: config.fee_bps,
+            max_issuer_fee_bps: config.max_issuer_fee_bps,
+            fee_receiver: config.fee_receiver,
+            paused: config.paused,
+        });
+
+        Ok(())
+    }
+
+    /// Registers a KYC provider key allowed to write investor credentials.
+    pub fn register_kyc_provider(ctx: Context<RegisterKycProvider>) -> Result<()> {
+        let provider = &mut ctx.accounts.kyc_provider;
+        provider.authority = ctx.accounts.provider_authority.key();
+        provider.active = true;
+        provider.bump = ctx.bumps.kyc_provider;
+
+        emit!(KycProviderUpdated {
+            kyc_provider: provider.key(),
+            authority: provider.authority,
+            active: provider.active,
+        });
+
+        Ok(())
+    }
+
+    /// Credentials of an inactive provider stop being valid. The provider is
+    /// also listed in `disabled_kyc_providers`, which the transfer hook reads.
+    pub fn set_kyc_provider_active(ctx: Context<SetKycProviderActive>, active: bool) -> Result<()> {
+        let provider = &mut ctx.accounts.kyc_provider;
+        provider.active = active;
+
+        let disabled = &mut ctx.accounts.disabled_kyc_providers;
+        disabled.bump = ctx.bumps.disabled_kyc_providers;
+        disabled.providers.retain(|key| *key != provider.key());
+        if !active {
+            require!(
+                disabled.providers.len() < MAX_DISABLED_KYC_PROVIDERS,
+                ErrorCode::TooManyDisabledKycProviders
+            );
+            disabled.providers.push(provider.key());
+        }
+
+        emit!(KycProviderUpdated {
+            kyc_provider: provider.key(),
+            authority: provider.authority,
+            active,
+        });
+
+        Ok(())
+    }
+
+    /// Creates or overwrites the credential of `wallet`. Signed by an active
+    /// provider; an existing credential can only be rewritten by the provider
+    /// that issued it, or by another provider with the admin co-signing.
+    pub fn set_credential(
+        ctx: Context<SetCredential>,
+        status: KycStatus,
+        tier: u8,
+        country: [u8; 2],
+        expires_at: i64,
+    ) -> Result<()> {
+        require!(is_country_code(&country), ErrorCode::InvalidCountryCode);
+
+        let issuer = ctx.accounts.credential.provider;
+        let admin_approved = ctx
+            .accounts
+            .admin
+            .as_ref()
+            .is_some_and(|admin| admin.key() == ctx.accounts.platform_config.admin);
+        require!(
+            issuer == Pubkey::default()
+                || issuer == ctx.accounts.kyc_provider.key()
+                || admin_approved,
+            ErrorCode::Unauthorized
+        );
+
+        let credential = &mut ctx.accounts.credential;
+        credential.wallet = ctx.accounts.wallet.key();
+        credential.provider = ctx.accounts.kyc_provider.key();
+        credential.status = status;
+        credential.tier = tier;
+        credential.country = country;
+        credential.expires_at = expires_at;
+        credential.updated_at = Clock::get()?.unix_timestamp;
+        credential.bump = ctx.bumps.credential;
+
+        emit!(CredentialUpdated {
+            wallet: credential.wallet,
+            provider: credential.provider,
+            status,
+            tier,
+            country,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Blocks `wallet` from every market instruction. Admin only.
+    pub fn add_to_denylist(ctx: Context<AddToDenylist>, reason: u8) -> Result<()> {
+        let entry = &mut ctx.accounts.denylist_entry;
+        entry.wallet = ctx.accounts.wallet.key();
+        entry.reason = reason;
+        entry.added_at = Clock::get()?.unix_timestamp;
+        entry.bump = ctx.bumps.denylist_entry;
+
+        emit!(DenylistUpdated {
+            wallet: entry.wallet,
+            denied: true,
+            reason,
+        });
+
+        Ok(())
+    }
+
+    /// Lifts the block by closing the wallet's denylist entry.
+    pub fn remove_from_denylist(ctx: Context<RemoveFromDenylist>) -> Result<()> {
+        emit!(DenylistUpdated {
+            wallet: ctx.accounts.denylist_entry.wallet,
+            denied: false,
+            reason: ctx.accounts.denylist_entry.reason,
+        });
+
+        Ok(())
+    }
+
+    /// Registers an asset and creates its share mint under `share_token_program`.
+    /// Under Token-2022 the mint gets `rwa_transfer_hook` as its transfer hook,
+    /// so secondary transfers are checked against credentials and the
+    /// jurisdiction policy, and `mint_auth` as permanent delegate for forced
+    /// transfers. The hook's `initialize_extra_account_meta_list` must run
+    /// before the first transfer.
     pub fn create_asset(
         ctx: Context<CreateAsset>,
         price_usdc: u64,
         total_supply: u64,
         free_float: u64,
         decimals: u8,
+        metadata: AssetMetadata,
     ) -> Result<()> {
-        let asset = &mut ctx.accounts.asset;
         let asset_mint = &ctx.accounts.asset_mint;
         let usdc_mint = &ctx.accounts.usdc_mint;
         let issuer = &ctx.accounts.issuer;
 
+        require!(
+            ctx.accounts
+                .platform_config
+                .allowed_quote_mints
+                .contains(&usdc_mint.key()),
+            ErrorCode::QuoteMintNotAllowed
+        );
+        metadata.validate()?;
+
+        init_share_mint(ctx.accounts, decimals)?;
+
+        let asset = &mut ctx.accounts.asset;
+
         // Initialize asset account
         asset.issuer = issuer.key();
         asset.asset_mint = asset_mint.key();
@@ -29,10 +276,19 @@ pub mod rwa_registry {
         asset.price_usdc = price_usdc;
         asset.total_supply = total_supply;
         asset.free_float = free_float;
+        asset.minted_supply = 0;
+        asset.check_supply()?;
+        asset.metadata = metadata;
+        asset.status = AssetStatus::Draft;
+        asset.reset_policy();
         asset.bump_asset = ctx.bumps.asset;
         asset.bump_mint_auth = ctx.bumps.mint_authority;
         asset.bump_vault_usdc = ctx.bumps.vault_usdc;
 
+        let roles = &mut ctx.accounts.roles;
+        roles.asset = asset.key();
+        roles.bump = ctx.bumps.roles;
+
         emit!(AssetCreated {
             asset: asset.key(),
             issuer: issuer.key(),
@@ -40,6 +296,7 @@ pub mod rwa_registry {
             price_usdc,
             total_supply,
             free_float,
+            metadata: asset.metadata.clone(),
         });
 
         Ok(())
@@ -49,125 +306,3006 @@ pub mod rwa_registry {
         ctx: Context<UpdateAsset>,
         new_price_usdc: Option<u64>,
         new_free_float: Option<u64>,
+        new_metadata: Option<AssetMetadata>,
     ) -> Result<()> {
         let asset = &mut ctx.accounts.asset;
+        let roles = &ctx.accounts.roles;
+        let authority = ctx.accounts.authority.key();
+
+        require!(
+            asset.status != AssetStatus::Closed,
+            ErrorCode::InvalidAssetStatus
+        );
+        // The price authority may only touch the price; everything else is
+        // reserved for the issuer.
+        if new_price_usdc.is_some() {
+            require_keys_eq!(
+                authority,
+                roles.price_authority(asset),
+                ErrorCode::Unauthorized
+            );
+        }
+        if new_free_float.is_some() || new_metadata.is_some() {
+            require_keys_eq!(authority, asset.issuer, ErrorCode::Unauthorized);
+        }
 
         if let Some(price) = new_price_usdc {
             asset.price_usdc = price;
         }
 
         if let Some(float) = new_free_float {
-            require!(float <= asset.total_supply, ErrorCode::InvalidFreeFloat);
             asset.free_float = float;
+            asset.check_supply()?;
+        }
+
+        if let Some(metadata) = new_metadata {
+            metadata.validate()?;
+            asset.metadata = metadata;
         }
 
         emit!(AssetUpdated {
             asset: asset.key(),
+            asset_mint: asset.asset_mint,
             price_usdc: asset.price_usdc,
             free_float: asset.free_float,
+            metadata: asset.metadata.clone(),
         });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(price_usdc: u64, total_supply: u64, free_float: u64, decimals: u8)]
-pub struct CreateAsset<'info> {
-    #[account(mut)]
-    pub issuer: Signer<'info>,
+    /// Assigns per-asset operational roles. `None` leaves a role with the issuer.
+    pub fn set_asset_roles(
+        ctx: Context<SetAssetRoles>,
+        price_oracle: Option<Pubkey>,
+        compliance_officer: Option<Pubkey>,
+        treasurer: Option<Pubkey>,
+    ) -> Result<()> {
+        let roles = &mut ctx.accounts.roles;
+        roles.price_oracle = price_oracle;
+        roles.compliance_officer = compliance_officer;
+        roles.treasurer = treasurer;
 
-    #[account(
-        init,
-        payer = issuer,
-        space = 8 + Asset::INIT_SPACE,
-        seeds = [b"asset", issuer.key().as_ref(), asset_mint.key().as_ref()],
-        bump
-    )]
-    pub asset: Account<'info, Asset>,
+        emit!(AssetRolesUpdated {
+            asset: ctx.accounts.asset.key(),
+            price_oracle,
+            compliance_officer,
+            treasurer,
+        });
 
-    #[account(
-        init,
-        payer = issuer,
-        mint::decimals = decimals,
-        mint::authority = mint_authority,
-        mint::freeze_authority = mint_authority,
-    )]
-    pub asset_mint: Account<'info, Mint>,
+        Ok(())
+    }
 
-    /// CHECK: This is the USDC mint address, validated by the client
-    pub usdc_mint: Account<'info, Mint>,
+    /// Replaces the asset's country allow/deny lists. Signed by the compliance authority.
+    pub fn set_jurisdiction_policy(
+        ctx: Context<SetJurisdictionPolicy>,
+        policy: JurisdictionPolicy,
+    ) -> Result<()> {
+        policy.validate()?;
 
-    #[account(
-        seeds = [b"mint_auth", asset_mint.key().as_ref()],
-        bump
-    )]
-    /// CHECK: This is a PDA used as mint authority
-    pub mint_authority: UncheckedAccount<'info>,
+        let asset = &mut ctx.accounts.asset;
+        asset.jurisdiction = policy;
 
-    #[account(
-        init,
-        payer = issuer,
-        token::mint = usdc_mint,
-        token::authority = vault_usdc,
-        seeds = [b"vault_usdc", asset_mint.key().as_ref()],
-        bump
-    )]
-    pub vault_usdc: Account<'info, TokenAccount>,
+        emit!(JurisdictionPolicyUpdated {
+            asset: asset.key(),
+            allowed: asset.jurisdiction.allowed.clone(),
+            blocked: asset.jurisdiction.blocked.clone(),
+        });
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct UpdateAsset<'info> {
-    #[account(mut)]
-    pub issuer: Signer<'info>,
+    /// Sets the per-wallet share cap and the maximum holder count enforced by
+    /// the market on buys. Zero disables a limit.
+    pub fn set_holder_limits(
+        ctx: Context<SetHolderLimits>,
+        max_shares_per_wallet: u64,
+        max_investors: u32,
+    ) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+        asset.max_shares_per_wallet = max_shares_per_wallet;
+        asset.max_investors = max_investors;
 
-    #[account(
-        mut,
-        seeds = [b"asset", issuer.key().as_ref(), asset.asset_mint.as_ref()],
-        bump = asset.bump_asset,
-        has_one = issuer
-    )]
-    pub asset: Account<'info, Asset>,
-}
+        emit!(HolderLimitsUpdated {
+            asset: asset.key(),
+            max_shares_per_wallet,
+            max_investors,
+        });
 
-#[account]
-#[derive(InitSpace)]
-pub struct Asset {
-    pub issuer: Pubkey,        // 32 bytes
-    pub asset_mint: Pubkey,    // 32 bytes
-    pub usdc_mint: Pubkey,     // 32 bytes
-    pub decimals: u8,          // 1 byte
-    pub price_usdc: u64,       // 8 bytes
-    pub total_supply: u64,     // 8 bytes
-    pub free_float: u64,       // 8 bytes
-    pub bump_asset: u8,        // 1 byte
-    pub bump_mint_auth: u8,    // 1 byte
-    pub bump_vault_usdc: u8,   // 1 byte
-}
+        Ok(())
+    }
 
-#[event]
-pub struct AssetCreated {
-    pub asset: Pubkey,
-    pub issuer: Pubkey,
-    pub asset_mint: Pubkey,
-    pub price_usdc: u64,
-    pub total_supply: u64,
-    pub free_float: u64,
-}
+    /// Sets the holding period applied to each purchase before the shares may
+    /// be sold or transferred. Zero disables the lockup for new purchases.
+    pub fn set_lockup_period(ctx: Context<SetLockupPeriod>, lockup_secs: i64) -> Result<()> {
+        require!(lockup_secs >= 0, ErrorCode::InvalidLockupPeriod);
 
-#[event]
-pub struct AssetUpdated {
-    pub asset: Pubkey,
-    pub price_usdc: u64,
-    pub free_float: u64,
-}
+        let asset = &mut ctx.accounts.asset;
+        asset.lockup_secs = lockup_secs;
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Invalid free float amount")]
-    InvalidFreeFloat,
-}
\ No newline at end of file
+        emit!(LockupPeriodUpdated {
+            asset: asset.key(),
+            lockup_secs,
+        });
+
+        Ok(())
+    }
+
+    /// Freezes a holder's share account through the `mint_auth` freeze authority.
+    /// `reason` is an off-chain code (court order, investigation, ...).
+    pub fn freeze_holder(ctx: Context<FreezeHolder>, reason: u8) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset.asset_mint.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            FreezeAccount {
+                account: ctx.accounts.holder_shares.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::freeze_account(cpi_ctx)?;
+
+        emit!(HolderFrozen {
+            asset: asset.key(),
+            holder: ctx.accounts.holder_shares.owner,
+            token_account: ctx.accounts.holder_shares.key(),
+            authority: ctx.accounts.authority.key(),
+            reason,
+        });
+
+        Ok(())
+    }
+
+    /// Thaws a share account frozen by `freeze_holder`. Accounts still holding
+    /// recalled shares (see `forced_transfer`) stay frozen.
+    pub fn thaw_holder(ctx: Context<FreezeHolder>, reason: u8) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        require!(
+            !recall_pending(&ctx.accounts.recall_lock)?,
+            ErrorCode::RecallPending
+        );
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset.asset_mint.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            ThawAccount {
+                account: ctx.accounts.holder_shares.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::thaw_account(cpi_ctx)?;
+
+        emit!(HolderThawed {
+            asset: asset.key(),
+            holder: ctx.accounts.holder_shares.owner,
+            token_account: ctx.accounts.holder_shares.key(),
+            authority: ctx.accounts.authority.key(),
+            reason,
+        });
+
+        Ok(())
+    }
+
+    /// Court-ordered move of `amount` shares between holders: burns from the
+    /// source and mints the same amount to the destination. Token-2022 share
+    /// mints burn through the `mint_auth` permanent delegate; a frozen source
+    /// is thawed for the burn and frozen again afterwards.
+    ///
+    /// Legacy token mints are freeze-only: the program can burn only what the
+    /// holder approved to `mint_auth`. Otherwise the source is frozen, nothing
+    /// is minted, and `recall_lock` records the shares and their destination
+    /// until `settle_recall` moves them. Pass `recall_lock` only in that case.
+    pub fn forced_transfer(
+        ctx: Context<ForcedTransfer>,
+        amount: u64,
+        reason: u8,
+        document_hash: [u8; 32],
+    ) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let source = &ctx.accounts.source_shares;
+        let destination = ctx.accounts.destination_shares.key();
+
+        require!(
+            source.key() != destination && amount > 0,
+            ErrorCode::InvalidForcedTransfer
+        );
+        require!(source.amount >= amount, ErrorCode::InsufficientShares);
+        let can_burn = can_recall_burn(
+            &ctx.accounts.asset_mint.to_account_info(),
+            source,
+            &ctx.accounts.mint_authority.key(),
+            amount,
+        );
+
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset.asset_mint.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let freeze = |frozen| {
+            set_frozen(
+                &token_program,
+                &source.to_account_info(),
+                &ctx.accounts.asset_mint.to_account_info(),
+                &ctx.accounts.mint_authority.to_account_info(),
+                signer,
+                frozen,
+            )
+        };
+
+        if can_burn {
+            require!(
+                ctx.accounts.recall_lock.is_none(),
+                ErrorCode::InvalidForcedTransfer
+            );
+            let was_frozen = source.is_frozen();
+            if was_frozen {
+                freeze(false)?;
+            }
+            token_interface::burn(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    Burn {
+                        mint: ctx.accounts.asset_mint.to_account_info(),
+                        from: source.to_account_info(),
+                        authority: ctx.accounts.mint_authority.to_account_info(),
+                    },
+                    signer,
+                ),
+                amount,
+            )?;
+            if was_frozen {
+                freeze(true)?;
+            }
+            token_interface::mint_to(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    MintTo {
+                        mint: ctx.accounts.asset_mint.to_account_info(),
+                        to: ctx.accounts.destination_shares.to_account_info(),
+                        authority: ctx.accounts.mint_authority.to_account_info(),
+                    },
+                    signer,
+                ),
+                amount,
+            )?;
+        } else {
+            let recall_lock = ctx
+                .accounts
+                .recall_lock
+                .as_deref_mut()
+                .ok_or(ErrorCode::DelegateNotApproved)?;
+            require!(
+                recall_lock.amount == 0 || recall_lock.destination == destination,
+                ErrorCode::InvalidForcedTransfer
+            );
+            let pending = recall_lock
+                .amount
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(source.amount >= pending, ErrorCode::InsufficientShares);
+            if !source.is_frozen() {
+                freeze(true)?;
+            }
+            recall_lock.token_account = source.key();
+            recall_lock.destination = destination;
+            recall_lock.amount = pending;
+            recall_lock.bump = ctx.bumps.recall_lock.unwrap_or_default();
+        }
+
+        emit!(ForcedTransferExecuted {
+            asset: asset.key(),
+            from: source.owner,
+            to: ctx.accounts.destination_shares.owner,
+            amount,
+            authority: ctx.accounts.authority.key(),
+            reason,
+            document_hash,
+            burned: can_burn,
+        });
+
+        Ok(())
+    }
+
+    /// Moves the shares held under a recall lock to its destination: burns
+    /// them from the frozen source, mints them to the destination and closes
+    /// the lock. The burn is signed by `mint_auth` once it is a delegate for
+    /// the amount, otherwise by the holder. The source stays frozen until
+    /// `thaw_holder`.
+    pub fn settle_recall(ctx: Context<SettleRecall>) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let source = &ctx.accounts.source_shares;
+        let amount = ctx.accounts.recall_lock.amount;
+
+        let by_delegate = can_recall_burn(
+            &ctx.accounts.asset_mint.to_account_info(),
+            source,
+            &ctx.accounts.mint_authority.key(),
+            amount,
+        );
+        let burn_authority = if by_delegate {
+            ctx.accounts.mint_authority.to_account_info()
+        } else {
+            let holder = ctx
+                .accounts
+                .holder
+                .as_ref()
+                .ok_or(ErrorCode::DelegateNotApproved)?;
+            require_keys_eq!(holder.key(), source.owner, ErrorCode::Unauthorized);
+            holder.to_account_info()
+        };
+
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset.asset_mint.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let freeze = |frozen| {
+            set_frozen(
+                &token_program,
+                &source.to_account_info(),
+                &ctx.accounts.asset_mint.to_account_info(),
+                &ctx.accounts.mint_authority.to_account_info(),
+                signer,
+                frozen,
+            )
+        };
+
+        if source.is_frozen() {
+            freeze(false)?;
+        }
+        token_interface::burn(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                Burn {
+                    mint: ctx.accounts.asset_mint.to_account_info(),
+                    from: source.to_account_info(),
+                    authority: burn_authority,
+                },
+                signer,
+            ),
+            amount,
+        )?;
+        freeze(true)?;
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                MintTo {
+                    mint: ctx.accounts.asset_mint.to_account_info(),
+                    to: ctx.accounts.destination_shares.to_account_info(),
+                    authority: ctx.accounts.mint_authority.to_account_info(),
+                },
+                signer,
+            ),
+            amount,
+        )?;
+
+        emit!(RecallSettled {
+            asset: asset.key(),
+            token_account: source.key(),
+            destination: ctx.accounts.destination_shares.key(),
+            amount,
+            authority: ctx.accounts.authority.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Moves the asset through its lifecycle. Callable by the issuer or the
+    /// platform admin; see `AssetStatus::can_transition_to` for allowed moves.
+    pub fn set_asset_status(ctx: Context<SetAssetStatus>, new_status: AssetStatus) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+        let old_status = asset.status;
+
+        require!(
+            old_status.can_transition_to(new_status),
+            ErrorCode::InvalidStatusTransition
+        );
+        asset.status = new_status;
+
+        emit!(AssetStatusChanged {
+            asset: asset.key(),
+            authority: ctx.accounts.authority.key(),
+            old_status,
+            new_status,
+        });
+
+        Ok(())
+    }
+
+    /// Publishes a new version of the offering documents. Investors must
+    /// `accept_terms` for this hash before their next purchase.
+    pub fn set_offering_documents(
+        ctx: Context<SetOfferingDocuments>,
+        documents_hash: [u8; 32],
+    ) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+        asset.documents_hash = documents_hash;
+
+        emit!(OfferingDocumentsUpdated {
+            asset: asset.key(),
+            documents_hash,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the issuer's fee on market buys and sells, charged on top of the
+    /// platform fee and kept in the asset vault. Zero disables it. Capped by
+    /// the platform's `max_issuer_fee_bps`.
+    pub fn set_issuer_fee(ctx: Context<SetIssuerFee>, issuer_fee_bps: u16) -> Result<()> {
+        require!(
+            issuer_fee_bps <= ctx.accounts.platform_config.max_issuer_fee_bps,
+            ErrorCode::IssuerFeeTooHigh
+        );
+
+        let asset = &mut ctx.accounts.asset;
+        asset.issuer_fee_bps = issuer_fee_bps;
+
+        emit!(IssuerFeeUpdated {
+            asset: asset.key(),
+            issuer_fee_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Records that `wallet` accepted the asset's current offering documents,
+    /// including their risk disclosures. `documents_hash` must match the
+    /// published version so an investor never accepts a stale document.
+    pub fn accept_terms(ctx: Context<AcceptTerms>, documents_hash: [u8; 32]) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        require!(
+            documents_hash == asset.documents_hash,
+            ErrorCode::DocumentsHashMismatch
+        );
+
+        let acceptance = &mut ctx.accounts.acceptance;
+        acceptance.asset = asset.key();
+        acceptance.wallet = ctx.accounts.wallet.key();
+        acceptance.documents_hash = documents_hash;
+        acceptance.accepted_at = Clock::get()?.unix_timestamp;
+        acceptance.bump = ctx.bumps.acceptance;
+
+        emit!(TermsAccepted {
+            asset: acceptance.asset,
+            wallet: acceptance.wallet,
+            documents_hash,
+            accepted_at: acceptance.accepted_at,
+        });
+
+        Ok(())
+    }
+
+    /// First step of an issuer handover. Passing `None` cancels a pending proposal.
+    pub fn propose_issuer_transfer(
+        ctx: Context<ProposeIssuerTransfer>,
+        new_issuer: Option<Pubkey>,
+    ) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+        asset.pending_issuer = new_issuer;
+
+        emit!(IssuerTransferProposed {
+            asset: asset.key(),
+            issuer: asset.issuer,
+            pending_issuer: new_issuer,
+        });
+
+        Ok(())
+    }
+
+    /// Second step of an issuer handover, signed by the proposed issuer.
+    pub fn accept_issuer_transfer(ctx: Context<AcceptIssuerTransfer>) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+        let old_issuer = asset.issuer;
+
+        asset.issuer = ctx.accounts.new_issuer.key();
+        asset.pending_issuer = None;
+
+        emit!(IssuerTransferred {
+            asset: asset.key(),
+            old_issuer,
+            new_issuer: asset.issuer,
+        });
+
+        Ok(())
+    }
+
+    /// Moves an asset created under the legacy `[b"asset", issuer, mint]` seeds
+    /// to the `[b"asset", mint]` PDA and closes the legacy account. Legacy
+    /// assets predate metadata and lifecycle fields, so the issuer supplies
+    /// metadata and the asset resumes in `Active` status.
+    pub fn migrate_asset(ctx: Context<MigrateAsset>, metadata: AssetMetadata) -> Result<()> {
+        metadata.validate()?;
+
+        let legacy_info = ctx.accounts.legacy_asset.to_account_info();
+        let legacy = {
+            let data = legacy_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == *Asset::DISCRIMINATOR,
+                ErrorCode::InvalidLegacyAsset
+            );
+            LegacyAsset::deserialize(&mut &data[8..])?
+        };
+        require!(
+            legacy.issuer == ctx.accounts.issuer.key()
+                && legacy.asset_mint == ctx.accounts.asset_mint.key(),
+            ErrorCode::InvalidLegacyAsset
+        );
+
+        let asset = &mut ctx.accounts.asset;
+        asset.issuer = legacy.issuer;
+        asset.asset_mint = legacy.asset_mint;
+        asset.usdc_mint = legacy.usdc_mint;
+        asset.decimals = legacy.decimals;
+        asset.price_usdc = legacy.price_usdc;
+        asset.total_supply = legacy.total_supply;
+        asset.free_float = legacy.free_float;
+        asset.minted_supply = ctx.accounts.asset_mint.supply;
+        asset.check_supply()?;
+        asset.metadata = metadata;
+        asset.status = AssetStatus::Active;
+        asset.reset_policy();
+        asset.bump_asset = ctx.bumps.asset;
+        asset.bump_mint_auth = legacy.bump_mint_auth;
+        asset.bump_vault_usdc = legacy.bump_vault_usdc;
+
+        let roles = &mut ctx.accounts.roles;
+        roles.asset = asset.key();
+        roles.bump = ctx.bumps.roles;
+
+        // Close the legacy account: refund rent and hand it back to the system program.
+        let issuer_info = ctx.accounts.issuer.to_account_info();
+        let lamports = legacy_info.lamports();
+        **issuer_info.try_borrow_mut_lamports()? = issuer_info
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(ErrorCode::MathOverflow)?;
+        **legacy_info.try_borrow_mut_lamports()? = 0;
+        legacy_info.assign(&system_program::ID);
+        legacy_info.resize(0)?;
+
+        emit!(AssetMigrated {
+            asset: asset.key(),
+            legacy_asset: legacy_info.key(),
+            issuer: asset.issuer,
+        });
+
+        Ok(())
+    }
+
+    /// Creates Metaplex token metadata for the share mint from `Asset.metadata`.
+    /// The `mint_auth` PDA is both mint and update authority.
+    pub fn create_share_metadata(ctx: Context<CreateShareMetadata>) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+
+        let asset_mint_key = asset.asset_mint;
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset_mint_key.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_metadata_program.to_account_info(),
+            CreateMetadataAccountsV3 {
+                metadata: ctx.accounts.share_metadata.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                payer: ctx.accounts.issuer.to_account_info(),
+                update_authority: ctx.accounts.mint_authority.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+            signer,
+        );
+        metadata::create_metadata_accounts_v3(cpi_ctx, share_metadata(asset), true, true, None)?;
+
+        emit!(ShareMetadataSynced {
+            asset: asset.key(),
+            share_metadata: ctx.accounts.share_metadata.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Pushes the current `Asset.metadata` to the share mint's token metadata,
+    /// e.g. after `update_asset` changed name, ticker or URI.
+    pub fn update_share_metadata(ctx: Context<UpdateShareMetadata>) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+
+        let asset_mint_key = asset.asset_mint;
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset_mint_key.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_metadata_program.to_account_info(),
+            UpdateMetadataAccountsV2 {
+                metadata: ctx.accounts.share_metadata.to_account_info(),
+                update_authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer,
+        );
+        metadata::update_metadata_accounts_v2(
+            cpi_ctx,
+            None,
+            Some(share_metadata(asset)),
+            None,
+            None,
+        )?;
+
+        emit!(ShareMetadataSynced {
+            asset: asset.key(),
+            share_metadata: ctx.accounts.share_metadata.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Mints the issuer's retained stake (`total_supply` not offered as float)
+    /// into a program-owned escrow that unlocks at `lock_until`.
+    pub fn mint_retained(ctx: Context<MintRetained>, lock_until: i64) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+
+        require!(
+            asset.status != AssetStatus::Closed,
+            ErrorCode::InvalidAssetStatus
+        );
+        let amount = asset
+            .total_supply
+            .checked_sub(asset.free_float)
+            .and_then(|rest| rest.checked_sub(asset.minted_supply))
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(amount > 0, ErrorCode::NothingToRetain);
+
+        let asset_mint_key = asset.asset_mint;
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset_mint_key.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                to: ctx.accounts.retained_escrow.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::mint_to(cpi_ctx, amount)?;
+
+        asset.minted_supply = asset
+            .minted_supply
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        asset.check_supply()?;
+
+        let retained = &mut ctx.accounts.retained;
+        retained.asset = asset.key();
+        retained.amount = amount;
+        retained.lock_until = lock_until;
+        retained.released = false;
+        retained.bump = ctx.bumps.retained;
+        retained.bump_escrow = ctx.bumps.retained_escrow;
+
+        emit!(RetainedMinted {
+            asset: asset.key(),
+            amount,
+            lock_until,
+        });
+
+        Ok(())
+    }
+
+    /// Sends the retained stake to the issuer once the lockup has expired.
+    /// Hooked share mints need the transfer hook accounts as remaining accounts.
+    pub fn release_retained<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseRetained<'info>>,
+    ) -> Result<()> {
+        let retained = &mut ctx.accounts.retained;
+
+        require!(!retained.released, ErrorCode::AlreadyReleased);
+        require!(
+            Clock::get()?.unix_timestamp >= retained.lock_until,
+            ErrorCode::StillLocked
+        );
+
+        let asset_key = retained.asset;
+        let seeds: &[&[u8]] = &[b"retained", asset_key.as_ref(), &[retained.bump]];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.retained_escrow.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                to: ctx.accounts.issuer_shares.to_account_info(),
+                authority: retained.to_account_info(),
+            },
+            signer,
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        transfer_shares(cpi_ctx, retained.amount, ctx.accounts.asset_mint.decimals)?;
+
+        retained.released = true;
+
+        emit!(RetainedReleased {
+            asset: asset_key,
+            issuer: ctx.accounts.issuer.key(),
+            amount: retained.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Locks `amount` of the issuer's shares for `beneficiary` with a cliff
+    /// followed by linear release until `end_ts`.
+    pub fn create_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateVesting<'info>>,
+        grant_id: u64,
+        amount: u64,
+        schedule: VestingSchedule,
+        category: VestingCategory,
+        revocable: bool,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidVestingSchedule);
+        schedule.validate()?;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.issuer_shares.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                to: ctx.accounts.vesting_vault.to_account_info(),
+                authority: ctx.accounts.issuer.to_account_info(),
+            },
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        transfer_shares(cpi_ctx, amount, ctx.accounts.asset_mint.decimals)?;
+
+        let grant = &mut ctx.accounts.grant;
+        grant.asset = ctx.accounts.asset.key();
+        grant.beneficiary = ctx.accounts.beneficiary.key();
+        grant.grant_id = grant_id;
+        grant.category = category;
+        grant.total_amount = amount;
+        grant.released_amount = 0;
+        grant.schedule = schedule;
+        grant.revocable = revocable;
+        grant.revoked = false;
+        grant.bump = ctx.bumps.grant;
+        grant.bump_vault = ctx.bumps.vesting_vault;
+
+        emit!(VestingCreated {
+            grant: grant.key(),
+            asset: grant.asset,
+            beneficiary: grant.beneficiary,
+            category,
+            amount,
+            schedule,
+            revocable,
+        });
+
+        Ok(())
+    }
+
+    /// Transfers whatever has vested so far to the beneficiary. Permissionless.
+    pub fn release_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReleaseVesting<'info>>,
+    ) -> Result<()> {
+        let grant = &mut ctx.accounts.grant;
+
+        let vested = grant.vested_amount(Clock::get()?.unix_timestamp)?;
+        let amount = vested
+            .checked_sub(grant.released_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(amount > 0, ErrorCode::NothingToRelease);
+
+        let asset_key = grant.asset;
+        let beneficiary_key = grant.beneficiary;
+        let grant_id = grant.grant_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            b"vesting",
+            asset_key.as_ref(),
+            beneficiary_key.as_ref(),
+            &grant_id,
+            &[grant.bump],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vesting_vault.to_account_info(),
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                to: ctx.accounts.beneficiary_shares.to_account_info(),
+                authority: grant.to_account_info(),
+            },
+            signer,
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        transfer_shares(cpi_ctx, amount, ctx.accounts.asset_mint.decimals)?;
+
+        grant.released_amount = vested;
+
+        emit!(VestingReleased {
+            grant: grant.key(),
+            beneficiary: beneficiary_key,
+            amount,
+            released_amount: grant.released_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the unvested part of a revocable grant to the issuer. Shares
+    /// vested up to now stay claimable by the beneficiary.
+    pub fn revoke_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevokeVesting<'info>>,
+    ) -> Result<()> {
+        let grant = &mut ctx.accounts.grant;
+
+        require!(grant.revocable, ErrorCode::NotRevocable);
+        require!(!grant.revoked, ErrorCode::AlreadyRevoked);
+
+        let vested = grant.vested_amount(Clock::get()?.unix_timestamp)?;
+        let unvested = grant
+            .total_amount
+            .checked_sub(vested)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if unvested > 0 {
+            let asset_key = grant.asset;
+            let beneficiary_key = grant.beneficiary;
+            let grant_id = grant.grant_id.to_le_bytes();
+            let seeds: &[&[u8]] = &[
+                b"vesting",
+                asset_key.as_ref(),
+                beneficiary_key.as_ref(),
+                &grant_id,
+                &[grant.bump],
+            ];
+            let signer = &[seeds];
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vesting_vault.to_account_info(),
+                    mint: ctx.accounts.asset_mint.to_account_info(),
+                    to: ctx.accounts.issuer_shares.to_account_info(),
+                    authority: grant.to_account_info(),
+                },
+                signer,
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+            transfer_shares(cpi_ctx, unvested, ctx.accounts.asset_mint.decimals)?;
+        }
+
+        grant.total_amount = vested;
+        grant.revoked = true;
+
+        emit!(VestingRevoked {
+            grant: grant.key(),
+            beneficiary: grant.beneficiary,
+            vested_amount: vested,
+            returned_amount: unvested,
+        });
+
+        Ok(())
+    }
+
+    /// Takes `amount` out of free float and mints it to `recipient`.
+    /// Callable only by the market program via CPI.
+    pub fn reserve_float(ctx: Context<ReserveFloat>, amount: u64) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+
+        require!(asset.free_float >= amount, ErrorCode::InsufficientFloat);
+        let minted = asset
+            .minted_supply
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(minted <= asset.total_supply, ErrorCode::SupplyCapExceeded);
+
+        let asset_mint_key = asset.asset_mint;
+        let seeds: &[&[u8]] = &[
+            b"mint_auth",
+            asset_mint_key.as_ref(),
+            &[asset.bump_mint_auth],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.asset_mint.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::mint_to(cpi_ctx, amount)?;
+
+        asset.free_float = asset
+            .free_float
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        asset.minted_supply = minted;
+
+        emit!(FloatReserved {
+            asset: asset.key(),
+            amount,
+            free_float: asset.free_float,
+            minted_supply: asset.minted_supply,
+        });
+
+        Ok(())
+    }
+
+    /// Returns `amount` of burned shares to free float.
+    /// Callable only by the market program via CPI.
+    pub fn release_float(ctx: Context<ReleaseFloat>, amount: u64) -> Result<()> {
+        let asset = &mut ctx.accounts.asset;
+
+        asset.minted_supply = asset
+            .minted_supply
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        asset.free_float = asset
+            .free_float
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        asset.check_supply()?;
+
+        emit!(FloatReleased {
+            asset: asset.key(),
+            amount,
+            free_float: asset.free_float,
+            minted_supply: asset.minted_supply,
+        });
+
+        Ok(())
+    }
+
+    /// Pays `amount` USDC out of the asset vault.
+    /// Callable only by the market program via CPI.
+    pub fn withdraw_vault(ctx: Context<WithdrawVault>, amount: u64) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+
+        let asset_mint_key = asset.asset_mint;
+        let seeds: &[&[u8]] = &[
+            b"vault_usdc",
+            asset_mint_key.as_ref(),
+            &[asset.bump_vault_usdc],
+        ];
+        let signer = &[seeds];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_usdc.to_account_info(),
+                mint: ctx.accounts.usdc_mint.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: ctx.accounts.vault_usdc.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.usdc_mint.decimals)
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializePlatform<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PlatformConfig::INIT_SPACE,
+        seeds = [b"platform_config"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, program::RwaRegistry>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePlatform<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterKycProvider<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    /// CHECK: Key the provider signs credentials with
+    pub provider_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + KycProvider::INIT_SPACE,
+        seeds = [b"kyc_provider", provider_authority.key().as_ref()],
+        bump
+    )]
+    pub kyc_provider: Account<'info, KycProvider>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetKycProviderActive<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"kyc_provider", kyc_provider.authority.as_ref()],
+        bump = kyc_provider.bump
+    )]
+    pub kyc_provider: Account<'info, KycProvider>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + DisabledKycProviders::INIT_SPACE,
+        seeds = [b"disabled_kyc_providers"],
+        bump
+    )]
+    pub disabled_kyc_providers: Account<'info, DisabledKycProviders>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCredential<'info> {
+    #[account(mut)]
+    pub provider_authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"kyc_provider", provider_authority.key().as_ref()],
+        bump = kyc_provider.bump,
+        constraint = kyc_provider.active @ ErrorCode::KycProviderInactive
+    )]
+    pub kyc_provider: Account<'info, KycProvider>,
+
+    #[account(seeds = [b"platform_config"], bump = platform_config.bump)]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    /// Required when overwriting a credential another provider issued
+    pub admin: Option<Signer<'info>>,
+
+    /// CHECK: Investor wallet the credential is issued for
+    pub wallet: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = provider_authority,
+        space = 8 + Credential::INIT_SPACE,
+        seeds = [b"credential", wallet.key().as_ref()],
+        bump
+    )]
+    pub credential: Account<'info, Credential>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddToDenylist<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    /// CHECK: Wallet being blocked
+    pub wallet: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + DenylistEntry::INIT_SPACE,
+        seeds = [b"denylist", wallet.key().as_ref()],
+        bump
+    )]
+    pub denylist_entry: Account<'info, DenylistEntry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFromDenylist<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"denylist", denylist_entry.wallet.as_ref()],
+        bump = denylist_entry.bump
+    )]
+    pub denylist_entry: Account<'info, DenylistEntry>,
+}
+
+#[derive(Accounts)]
+#[instruction(price_usdc: u64, total_supply: u64, free_float: u64, decimals: u8)]
+pub struct CreateAsset<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        constraint = !platform_config.paused @ ErrorCode::PlatformPaused
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + Asset::INIT_SPACE,
+        seeds = [b"asset", asset_mint.key().as_ref()],
+        bump
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + AssetRoles::INIT_SPACE,
+        seeds = [b"roles", asset.key().as_ref()],
+        bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+
+    /// New share mint, created in the handler with the extensions its token
+    /// program supports
+    #[account(mut)]
+    pub asset_mint: Signer<'info>,
+
+    /// Quote mint, must be on the platform allowlist
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump
+    )]
+    /// CHECK: This is a PDA used as mint authority
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = issuer,
+        token::mint = usdc_mint,
+        token::authority = vault_usdc,
+        token::token_program = token_program,
+        seeds = [b"vault_usdc", asset_mint.key().as_ref()],
+        bump
+    )]
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
+
+    /// Token program of the quote mint
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the share mint, Token-2022 for hooked assets
+    pub share_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAsset<'info> {
+    /// Issuer, or the price oracle for price-only updates
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+}
+
+#[derive(Accounts)]
+pub struct SetAssetRoles<'info> {
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        mut,
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+}
+
+#[derive(Accounts)]
+pub struct SetJurisdictionPolicy<'info> {
+    #[account(
+        constraint = authority.key() == roles.compliance_authority(&asset) @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+}
+
+#[derive(Accounts)]
+pub struct SetHolderLimits<'info> {
+    #[account(
+        constraint = authority.key() == roles.compliance_authority(&asset) @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+}
+
+#[derive(Accounts)]
+pub struct SetLockupPeriod<'info> {
+    #[account(
+        constraint = authority.key() == roles.compliance_authority(&asset) @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeHolder<'info> {
+    #[account(
+        constraint = authority.key() == roles.compliance_authority(&asset) @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump = asset.bump_mint_auth
+    )]
+    /// CHECK: This is a PDA used as mint and freeze authority
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = asset_mint)]
+    pub holder_shares: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: recall lock PDA of `holder_shares`; thawing requires it to be
+    /// settled or never created
+    #[account(seeds = [b"recall", holder_shares.key().as_ref()], bump)]
+    pub recall_lock: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ForcedTransfer<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == roles.compliance_authority(&asset) @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+
+    #[account(mut)]
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump = asset.bump_mint_auth
+    )]
+    /// CHECK: This is a PDA used as mint authority and permanent delegate
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = asset_mint)]
+    pub source_shares: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = asset_mint)]
+    pub destination_shares: InterfaceAccount<'info, TokenAccount>,
+
+    /// Recalled shares left frozen in `source_shares`; required when they
+    /// cannot be burned, rejected otherwise
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + RecallLock::INIT_SPACE,
+        seeds = [b"recall", source_shares.key().as_ref()],
+        bump
+    )]
+    pub recall_lock: Option<Account<'info, RecallLock>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleRecall<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == roles.compliance_authority(&asset) @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"roles", asset.key().as_ref()],
+        bump = roles.bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+
+    #[account(mut)]
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump = asset.bump_mint_auth
+    )]
+    /// CHECK: This is a PDA used as mint and freeze authority
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = asset_mint)]
+    pub source_shares: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, address = recall_lock.destination)]
+    pub destination_shares: InterfaceAccount<'info, TokenAccount>,
+
+    /// Owner of `source_shares`, signs the burn when `mint_auth` is no delegate
+    pub holder: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"recall", source_shares.key().as_ref()],
+        bump = recall_lock.bump
+    )]
+    pub recall_lock: Account<'info, RecallLock>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetAssetStatus<'info> {
+    #[account(
+        constraint = authority.key() == asset.issuer
+            || authority.key() == platform_config.admin @ ErrorCode::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+}
+
+#[derive(Accounts)]
+pub struct SetOfferingDocuments<'info> {
+    pub issuer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer
+    )]
+    pub asset: Account<'info, Asset>,
+}
+
+#[derive(Accounts)]
+pub struct SetIssuerFee<'info> {
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer
+    )]
+    pub asset: Account<'info, Asset>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptTerms<'info> {
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        init_if_needed,
+        payer = wallet,
+        space = 8 + TermsAcceptance::INIT_SPACE,
+        seeds = [b"acceptance", asset.key().as_ref(), wallet.key().as_ref()],
+        bump
+    )]
+    pub acceptance: Account<'info, TermsAcceptance>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeIssuerTransfer<'info> {
+    pub issuer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer
+    )]
+    pub asset: Account<'info, Asset>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptIssuerTransfer<'info> {
+    pub new_issuer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        constraint = asset.pending_issuer == Some(new_issuer.key()) @ ErrorCode::Unauthorized
+    )]
+    pub asset: Account<'info, Asset>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAsset<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"asset", issuer.key().as_ref(), asset_mint.key().as_ref()],
+        bump
+    )]
+    /// CHECK: Legacy asset layout, deserialized and validated in the handler
+    pub legacy_asset: UncheckedAccount<'info>,
+
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + Asset::INIT_SPACE,
+        seeds = [b"asset", asset_mint.key().as_ref()],
+        bump
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + AssetRoles::INIT_SPACE,
+        seeds = [b"roles", asset.key().as_ref()],
+        bump
+    )]
+    pub roles: Account<'info, AssetRoles>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateShareMetadata<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump = asset.bump_mint_auth
+    )]
+    /// CHECK: This is a PDA used as mint and metadata update authority
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), asset_mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    /// CHECK: Initialized by the token metadata program
+    pub share_metadata: UncheckedAccount<'info>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateShareMetadata<'info> {
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        seeds = [b"mint_auth", asset.asset_mint.as_ref()],
+        bump = asset.bump_mint_auth
+    )]
+    /// CHECK: This is a PDA used as mint and metadata update authority
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), asset.asset_mint.as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    /// CHECK: Owned and validated by the token metadata program
+    pub share_metadata: UncheckedAccount<'info>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+}
+
+#[derive(Accounts)]
+pub struct MintRetained<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(mut)]
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump = asset.bump_mint_auth
+    )]
+    /// CHECK: This is a PDA used as mint authority
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + RetainedAllocation::INIT_SPACE,
+        seeds = [b"retained", asset.key().as_ref()],
+        bump
+    )]
+    pub retained: Account<'info, RetainedAllocation>,
+
+    #[account(
+        init,
+        payer = issuer,
+        token::mint = asset_mint,
+        token::authority = retained,
+        token::token_program = token_program,
+        seeds = [b"retained_escrow", asset.key().as_ref()],
+        bump
+    )]
+    pub retained_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseRetained<'info> {
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"retained", asset.key().as_ref()],
+        bump = retained.bump,
+        has_one = asset
+    )]
+    pub retained: Account<'info, RetainedAllocation>,
+
+    #[account(
+        mut,
+        seeds = [b"retained_escrow", asset.key().as_ref()],
+        bump = retained.bump_escrow
+    )]
+    pub retained_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = asset.asset_mint,
+        token::authority = issuer
+    )]
+    pub issuer_shares: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(grant_id: u64)]
+pub struct CreateVesting<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Any wallet may be a beneficiary
+    pub beneficiary: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = issuer,
+        space = 8 + VestingGrant::INIT_SPACE,
+        seeds = [
+            b"vesting",
+            asset.key().as_ref(),
+            beneficiary.key().as_ref(),
+            &grant_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub grant: Account<'info, VestingGrant>,
+
+    #[account(
+        init,
+        payer = issuer,
+        token::mint = asset_mint,
+        token::authority = grant,
+        token::token_program = token_program,
+        seeds = [b"vesting_vault", grant.key().as_ref()],
+        bump
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = asset_mint,
+        token::authority = issuer
+    )]
+    pub issuer_shares: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseVesting<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"vesting",
+            grant.asset.as_ref(),
+            grant.beneficiary.as_ref(),
+            &grant.grant_id.to_le_bytes()
+        ],
+        bump = grant.bump
+    )]
+    pub grant: Account<'info, VestingGrant>,
+
+    #[account(
+        mut,
+        seeds = [b"vesting_vault", grant.key().as_ref()],
+        bump = grant.bump_vault
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = vesting_vault.mint)]
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = vesting_vault.mint,
+        token::authority = grant.beneficiary
+    )]
+    pub beneficiary_shares: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeVesting<'info> {
+    pub issuer: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = issuer,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"vesting",
+            asset.key().as_ref(),
+            grant.beneficiary.as_ref(),
+            &grant.grant_id.to_le_bytes()
+        ],
+        bump = grant.bump,
+        has_one = asset
+    )]
+    pub grant: Account<'info, VestingGrant>,
+
+    #[account(
+        mut,
+        seeds = [b"vesting_vault", grant.key().as_ref()],
+        bump = grant.bump_vault
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = asset.asset_mint,
+        token::authority = issuer
+    )]
+    pub issuer_shares: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ReserveFloat<'info> {
+    #[account(
+        seeds = [b"market_authority"],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub market_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset,
+        has_one = asset_mint
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(mut)]
+    pub asset_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"mint_auth", asset_mint.key().as_ref()],
+        bump = asset.bump_mint_auth
+    )]
+    /// CHECK: This is a PDA used as mint authority
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = asset_mint)]
+    pub recipient: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseFloat<'info> {
+    #[account(
+        seeds = [b"market_authority"],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub market_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawVault<'info> {
+    #[account(
+        seeds = [b"market_authority"],
+        bump,
+        seeds::program = MARKET_PROGRAM_ID
+    )]
+    pub market_authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"asset", asset.asset_mint.as_ref()],
+        bump = asset.bump_asset
+    )]
+    pub asset: Account<'info, Asset>,
+
+    #[account(
+        mut,
+        seeds = [b"vault_usdc", asset.asset_mint.as_ref()],
+        bump = asset.bump_vault_usdc
+    )]
+    pub vault_usdc: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = asset.usdc_mint)]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, token::mint = asset.usdc_mint)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PlatformConfig {
+    pub admin: Pubkey,                      // 32 bytes
+    #[max_len(MAX_QUOTE_MINTS)]
+    pub allowed_quote_mints: Vec<Pubkey>,   // 4 + 32 * 8 bytes
+    pub fee_bps: u16,                       // 2 bytes
+    pub max_issuer_fee_bps: u16,            // 2 bytes, cap on every asset's issuer fee
+    pub fee_receiver: Pubkey,               // 32 bytes
+    pub paused: bool,                       // 1 byte
+    pub bump: u8,                           // 1 byte
+}
+
+impl PlatformConfig {
+    /// Issuer fee charged on `asset`. Clamped to the current cap, so lowering
+    /// the cap also applies to assets that set their fee before.
+    pub fn issuer_fee_bps(&self, asset: &Asset) -> u16 {
+        asset.issuer_fee_bps.min(self.max_issuer_fee_bps)
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct KycProvider {
+    pub authority: Pubkey,     // 32 bytes
+    pub active: bool,          // 1 byte
+    pub bump: u8,              // 1 byte
+}
+
+impl KycProvider {
+    /// PDA of this provider, `[b"kyc_provider", authority]`.
+    pub fn address(&self) -> Pubkey {
+        Pubkey::create_program_address(
+            &[b"kyc_provider", self.authority.as_ref(), &[self.bump]],
+            &crate::ID,
+        )
+        .unwrap_or_default()
+    }
+}
+
+/// Deactivated KYC providers. Token-2022 resolves this fixed PDA for the
+/// transfer hook, which cannot look up each credential's provider account.
+/// Seeds: `[b"disabled_kyc_providers"]`.
+#[account]
+#[derive(InitSpace)]
+pub struct DisabledKycProviders {
+    #[max_len(MAX_DISABLED_KYC_PROVIDERS)]
+    pub providers: Vec<Pubkey>, // 4 + 32 * 32 bytes
+    pub bump: u8,              // 1 byte
+}
+
+/// Latest offering documents version a wallet accepted for an asset.
+#[account]
+#[derive(InitSpace)]
+pub struct TermsAcceptance {
+    pub asset: Pubkey,         // 32 bytes
+    pub wallet: Pubkey,        // 32 bytes
+    pub documents_hash: [u8; 32], // 32 bytes
+    pub accepted_at: i64,      // 8 bytes
+    pub bump: u8,              // 1 byte
+}
+
+/// Shares recalled by `forced_transfer` that could not be burned and stay
+/// frozen in `token_account` until `settle_recall` moves them to
+/// `destination`. Seeds: `[b"recall", token_account]`.
+#[account]
+#[derive(InitSpace)]
+pub struct RecallLock {
+    pub token_account: Pubkey, // 32 bytes
+    pub destination: Pubkey,   // 32 bytes
+    pub amount: u64,           // 8 bytes
+    pub bump: u8,              // 1 byte
+}
+
+/// Sanctioned wallet. The entry existing at `[b"denylist", wallet]` is the block.
+#[account]
+#[derive(InitSpace)]
+pub struct DenylistEntry {
+    pub wallet: Pubkey,        // 32 bytes
+    pub reason: u8,            // 1 byte
+    pub added_at: i64,         // 8 bytes
+    pub bump: u8,              // 1 byte
+}
+
+/// Per-wallet KYC result written by a registered provider.
+#[account]
+#[derive(InitSpace)]
+pub struct Credential {
+    pub wallet: Pubkey,        // 32 bytes
+    pub provider: Pubkey,      // 32 bytes
+    pub status: KycStatus,     // 1 byte
+    pub tier: u8,              // 1 byte
+    pub country: [u8; 2],      // 2 bytes, ISO 3166-1 alpha-2
+    pub expires_at: i64,       // 8 bytes
+    pub updated_at: i64,       // 8 bytes
+    pub bump: u8,              // 1 byte
+}
+
+impl Credential {
+    /// Approved, not yet expired, and `provider` is the still active provider
+    /// that issued it.
+    pub fn is_valid(&self, provider: &KycProvider, now: i64) -> bool {
+        provider.active && provider.address() == self.provider && self.is_current(now)
+    }
+
+    /// Approved and not yet expired; says nothing about the issuing provider.
+    pub fn is_current(&self, now: i64) -> bool {
+        self.status == KycStatus::Approved && now < self.expires_at
+    }
+}
+
+/// Off-chain alternative to `Credential`: signed by a registered provider's
+/// `authority` key and verified through an ed25519 program instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct KycAttestation {
+    pub wallet: Pubkey,
+    /// Mint the attestation is scoped to; `Pubkey::default()` covers every asset.
+    pub asset_mint: Pubkey,
+    pub tier: u8,
+    pub country: [u8; 2],
+    pub expires_at: i64,
+}
+
+impl KycAttestation {
+    pub const DOMAIN: &'static [u8] = b"rwa-kyc-attestation-v1";
+
+    /// Bytes the provider signs: domain tag followed by the borsh-encoded attestation.
+    pub fn signing_message(&self) -> Result<Vec<u8>> {
+        let mut message = Self::DOMAIN.to_vec();
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+
+    pub fn covers(&self, wallet: &Pubkey, asset_mint: &Pubkey, now: i64) -> bool {
+        self.wallet == *wallet
+            && (self.asset_mint == Pubkey::default() || self.asset_mint == *asset_mint)
+            && now < self.expires_at
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum KycStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Revoked,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Asset {
+    pub issuer: Pubkey,        // 32 bytes
+    pub asset_mint: Pubkey,    // 32 bytes
+    pub usdc_mint: Pubkey,     // 32 bytes
+    pub decimals: u8,          // 1 byte
+    pub price_usdc: u64,       // 8 bytes
+    pub total_supply: u64,     // 8 bytes
+    pub free_float: u64,       // 8 bytes
+    pub minted_supply: u64,    // 8 bytes
+    pub metadata: AssetMetadata, // 257 bytes
+    pub status: AssetStatus,   // 1 byte
+    pub jurisdiction: JurisdictionPolicy, // 2 * (4 + 2 * 16) bytes
+    pub max_shares_per_wallet: u64, // 8 bytes, 0 = unlimited
+    pub max_investors: u32,    // 4 bytes, 0 = unlimited
+    pub lockup_secs: i64,      // 8 bytes, 0 = no lockup
+    pub documents_hash: [u8; 32], // 32 bytes, current offering documents
+    pub issuer_fee_bps: u16,   // 2 bytes
+    pub pending_issuer: Option<Pubkey>, // 1 + 32 bytes
+    pub bump_asset: u8,        // 1 byte
+    pub bump_mint_auth: u8,    // 1 byte
+    pub bump_vault_usdc: u8,   // 1 byte
+}
+
+impl Asset {
+    /// Circulating shares plus the float still for sale never exceed the declared total.
+    pub fn check_supply(&self) -> Result<()> {
+        let committed = self
+            .minted_supply
+            .checked_add(self.free_float)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(committed <= self.total_supply, ErrorCode::InvalidFreeFloat);
+        Ok(())
+    }
+
+    /// Compliance and fee settings of a freshly registered asset: no
+    /// jurisdiction, holding or lockup limits, no documents and no issuer fee.
+    fn reset_policy(&mut self) {
+        self.jurisdiction = JurisdictionPolicy::default();
+        self.max_shares_per_wallet = 0;
+        self.max_investors = 0;
+        self.lockup_secs = 0;
+        self.documents_hash = [0; 32];
+        self.issuer_fee_bps = 0;
+        self.pending_issuer = None;
+    }
+}
+
+/// Issuer's retained stake, held in `retained_escrow` until `lock_until`.
+#[account]
+#[derive(InitSpace)]
+pub struct RetainedAllocation {
+    pub asset: Pubkey,         // 32 bytes
+    pub amount: u64,           // 8 bytes
+    pub lock_until: i64,       // 8 bytes
+    pub released: bool,        // 1 byte
+    pub bump: u8,              // 1 byte
+    pub bump_escrow: u8,       // 1 byte
+}
+
+/// Share grant released linearly between `cliff_ts` and `end_ts`.
+#[account]
+#[derive(InitSpace)]
+pub struct VestingGrant {
+    pub asset: Pubkey,             // 32 bytes
+    pub beneficiary: Pubkey,       // 32 bytes
+    pub grant_id: u64,             // 8 bytes
+    pub category: VestingCategory, // 1 byte
+    pub total_amount: u64,         // 8 bytes
+    pub released_amount: u64,      // 8 bytes
+    pub schedule: VestingSchedule, // 24 bytes
+    pub revocable: bool,           // 1 byte
+    pub revoked: bool,             // 1 byte
+    pub bump: u8,                  // 1 byte
+    pub bump_vault: u8,            // 1 byte
+}
+
+impl VestingGrant {
+    /// Amount vested at `now`. Nothing before the cliff, everything after
+    /// `end_ts`, linear from `start_ts` in between. A revoked grant is frozen
+    /// at what had vested when it was revoked.
+    pub fn vested_amount(&self, now: i64) -> Result<u64> {
+        let schedule = &self.schedule;
+        if self.revoked || now >= schedule.end_ts {
+            return Ok(self.total_amount);
+        }
+        if now < schedule.cliff_ts {
+            return Ok(0);
+        }
+        let elapsed = (now - schedule.start_ts) as u128;
+        let duration = (schedule.end_ts - schedule.start_ts) as u128;
+        let vested = (self.total_amount as u128)
+            .checked_mul(elapsed)
+            .ok_or(ErrorCode::MathOverflow)?
+            / duration;
+        Ok(vested as u64)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct VestingSchedule {
+    pub start_ts: i64, // 8 bytes
+    pub cliff_ts: i64, // 8 bytes
+    pub end_ts: i64,   // 8 bytes
+}
+
+impl VestingSchedule {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.start_ts <= self.cliff_ts
+                && self.cliff_ts <= self.end_ts
+                && self.start_ts < self.end_ts,
+            ErrorCode::InvalidVestingSchedule
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum VestingCategory {
+    Founders,
+    Team,
+    Advisors,
+    Public,
+}
+
+/// Operational roles for one asset. Unassigned roles fall back to the issuer.
+#[account]
+#[derive(InitSpace)]
+pub struct AssetRoles {
+    pub asset: Pubkey,                      // 32 bytes
+    pub price_oracle: Option<Pubkey>,       // 1 + 32 bytes
+    pub compliance_officer: Option<Pubkey>, // 1 + 32 bytes
+    pub treasurer: Option<Pubkey>,          // 1 + 32 bytes
+    pub bump: u8,                           // 1 byte
+}
+
+impl AssetRoles {
+    /// Key allowed to change `price_usdc`.
+    pub fn price_authority(&self, asset: &Asset) -> Pubkey {
+        self.price_oracle.unwrap_or(asset.issuer)
+    }
+
+    /// Key allowed to run compliance actions on holder accounts.
+    pub fn compliance_authority(&self, asset: &Asset) -> Pubkey {
+        self.compliance_officer.unwrap_or(asset.issuer)
+    }
+
+    /// Key allowed to withdraw sale proceeds from the vault.
+    pub fn treasury_authority(&self, asset: &Asset) -> Pubkey {
+        self.treasurer.unwrap_or(asset.issuer)
+    }
+}
+
+/// Investor countries an asset may be sold to. An empty `allowed` list means
+/// every country not in `blocked`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct JurisdictionPolicy {
+    #[max_len(MAX_JURISDICTIONS)]
+    pub allowed: Vec<[u8; 2]>,
+    #[max_len(MAX_JURISDICTIONS)]
+    pub blocked: Vec<[u8; 2]>,
+}
+
+impl JurisdictionPolicy {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.allowed.len() <= MAX_JURISDICTIONS && self.blocked.len() <= MAX_JURISDICTIONS,
+            ErrorCode::TooManyJurisdictions
+        );
+        require!(
+            self.allowed
+                .iter()
+                .chain(&self.blocked)
+                .all(is_country_code),
+            ErrorCode::InvalidCountryCode
+        );
+        Ok(())
+    }
+
+    pub fn allows(&self, country: &[u8; 2]) -> bool {
+        !self.blocked.contains(country)
+            && (self.allowed.is_empty() || self.allowed.contains(country))
+    }
+}
+
+/// `Asset` layout used before assets were keyed by mint only.
+#[derive(AnchorDeserialize)]
+pub struct LegacyAsset {
+    pub issuer: Pubkey,
+    pub asset_mint: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub decimals: u8,
+    pub price_usdc: u64,
+    pub total_supply: u64,
+    pub free_float: u64,
+    pub bump_asset: u8,
+    pub bump_mint_auth: u8,
+    pub bump_vault_usdc: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum AssetStatus {
+    /// Registered, not yet open to investors.
+    Draft,
+    /// Primary sale is open.
+    Offering,
+    /// Primary sale, redemptions and dividends are live.
+    Active,
+    /// Trading halted; dividends can still be claimed.
+    Paused,
+    /// Wind-down: holders may only redeem and claim.
+    Redeeming,
+    /// Terminal state.
+    Closed,
+}
+
+impl AssetStatus {
+    pub fn can_transition_to(self, next: AssetStatus) -> bool {
+        use AssetStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Offering)
+                | (Offering, Active)
+                | (Offering | Active, Paused)
+                | (Paused, Offering | Active)
+                | (Offering | Active | Paused, Redeeming)
+                | (Redeeming, Closed)
+        )
+    }
+
+    /// Shares can be bought from free float.
+    pub fn allows_buy(self) -> bool {
+        matches!(self, AssetStatus::Offering | AssetStatus::Active)
+    }
+
+    /// Shares can be sold back to the vault.
+    pub fn allows_sell(self) -> bool {
+        matches!(self, AssetStatus::Active | AssetStatus::Redeeming)
+    }
+
+    /// Holders can trade shares with each other on the market order book.
+    pub fn allows_secondary_trading(self) -> bool {
+        matches!(self, AssetStatus::Active)
+    }
+
+    /// New dividend rounds can be opened.
+    pub fn allows_dividend_open(self) -> bool {
+        matches!(
+            self,
+            AssetStatus::Active | AssetStatus::Paused | AssetStatus::Redeeming
+        )
+    }
+
+    /// Sale proceeds can be withdrawn; blocked while holders are redeeming.
+    pub fn allows_proceeds_withdrawal(self) -> bool {
+        !matches!(self, AssetStatus::Draft | AssetStatus::Redeeming)
+    }
+
+    /// Open dividend rounds can be claimed.
+    pub fn allows_dividend_claim(self) -> bool {
+        !matches!(self, AssetStatus::Draft | AssetStatus::Offering)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum AssetCategory {
+    RealEstate,
+    Equipment,
+    Art,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct AssetMetadata {
+    #[max_len(MAX_NAME_LEN)]
+    pub name: String,          // 4 + 32 bytes
+    #[max_len(MAX_TICKER_LEN)]
+    pub ticker: String,        // 4 + 10 bytes
+    pub category: AssetCategory, // 1 byte
+    pub country: [u8; 2],      // 2 bytes, ISO 3166-1 alpha-2
+    #[max_len(MAX_URI_LEN)]
+    pub uri: String,           // 4 + 200 bytes
+}
+
+impl AssetMetadata {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.name.is_empty() && self.name.len() <= MAX_NAME_LEN,
+            ErrorCode::InvalidName
+        );
+        require!(
+            !self.ticker.is_empty() && self.ticker.len() <= MAX_TICKER_LEN,
+            ErrorCode::InvalidTicker
+        );
+        require!(is_country_code(&self.country), ErrorCode::InvalidCountryCode);
+        require!(self.uri.len() <= MAX_URI_LEN, ErrorCode::UriTooLong);
+        Ok(())
+    }
+}
+
+#[event]
+pub struct AssetCreated {
+    pub asset: Pubkey,
+    pub issuer: Pubkey,
+    pub asset_mint: Pubkey,
+    pub price_usdc: u64,
+    pub total_supply: u64,
+    pub free_float: u64,
+    pub metadata: AssetMetadata,
+}
+
+#[event]
+pub struct AssetUpdated {
+    pub asset: Pubkey,
+    pub asset_mint: Pubkey,
+    pub price_usdc: u64,
+    pub free_float: u64,
+    pub metadata: AssetMetadata,
+}
+
+#[event]
+pub struct AssetStatusChanged {
+    pub asset: Pubkey,
+    pub authority: Pubkey,
+    pub old_status: AssetStatus,
+    pub new_status: AssetStatus,
+}
+
+#[event]
+pub struct JurisdictionPolicyUpdated {
+    pub asset: Pubkey,
+    pub allowed: Vec<[u8; 2]>,
+    pub blocked: Vec<[u8; 2]>,
+}
+
+#[event]
+pub struct HolderLimitsUpdated {
+    pub asset: Pubkey,
+    pub max_shares_per_wallet: u64,
+    pub max_investors: u32,
+}
+
+#[event]
+pub struct LockupPeriodUpdated {
+    pub asset: Pubkey,
+    pub lockup_secs: i64,
+}
+
+#[event]
+pub struct HolderFrozen {
+    pub asset: Pubkey,
+    pub holder: Pubkey,
+    pub token_account: Pubkey,
+    pub authority: Pubkey,
+    pub reason: u8,
+}
+
+#[event]
+pub struct HolderThawed {
+    pub asset: Pubkey,
+    pub holder: Pubkey,
+    pub token_account: Pubkey,
+    pub authority: Pubkey,
+    pub reason: u8,
+}
+
+#[event]
+pub struct ForcedTransferExecuted {
+    pub asset: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub authority: Pubkey,
+    pub reason: u8,
+    pub document_hash: [u8; 32],
+    /// False when the shares stay frozen in the source under a recall lock
+    pub burned: bool,
+}
+
+#[event]
+pub struct RecallSettled {
+    pub asset: Pubkey,
+    pub token_account: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct AssetRolesUpdated {
+    pub asset: Pubkey,
+    pub price_oracle: Option<Pubkey>,
+    pub compliance_officer: Option<Pubkey>,
+    pub treasurer: Option<Pubkey>,
+}
+
+#[event]
+pub struct IssuerFeeUpdated {
+    pub asset: Pubkey,
+    pub issuer_fee_bps: u16,
+}
+
+#[event]
+pub struct OfferingDocumentsUpdated {
+    pub asset: Pubkey,
+    pub documents_hash: [u8; 32],
+}
+
+#[event]
+pub struct TermsAccepted {
+    pub asset: Pubkey,
+    pub wallet: Pubkey,
+    pub documents_hash: [u8; 32],
+    pub accepted_at: i64,
+}
+
+#[event]
+pub struct IssuerTransferProposed {
+    pub asset: Pubkey,
+    pub issuer: Pubkey,
+    pub pending_issuer: Option<Pubkey>,
+}
+
+#[event]
+pub struct IssuerTransferred {
+    pub asset: Pubkey,
+    pub old_issuer: Pubkey,
+    pub new_issuer: Pubkey,
+}
+
+#[event]
+pub struct AssetMigrated {
+    pub asset: Pubkey,
+    pub legacy_asset: Pubkey,
+    pub issuer: Pubkey,
+}
+
+#[event]
+pub struct ShareMetadataSynced {
+    pub asset: Pubkey,
+    pub share_metadata: Pubkey,
+}
+
+#[event]
+pub struct PlatformConfigUpdated {
+    pub admin: Pubkey,
+    pub allowed_quote_mints: Vec<Pubkey>,
+    pub fee_bps: u16,
+    pub max_issuer_fee_bps: u16,
+    pub fee_receiver: Pubkey,
+    pub paused: bool,
+}
+
+#[event]
+pub struct RetainedMinted {
+    pub asset: Pubkey,
+    pub amount: u64,
+    pub lock_until: i64,
+}
+
+#[event]
+pub struct RetainedReleased {
+    pub asset: Pubkey,
+    pub issuer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct VestingCreated {
+    pub grant: Pubkey,
+    pub asset: Pubkey,
+    pub beneficiary: Pubkey,
+    pub category: VestingCategory,
+    pub amount: u64,
+    pub schedule: VestingSchedule,
+    pub revocable: bool,
+}
+
+#[event]
+pub struct VestingReleased {
+    pub grant: Pubkey,
+    pub beneficiary: Pubkey,
+    pub amount: u64,
+    pub released_amount: u64,
+}
+
+#[event]
+pub struct VestingRevoked {
+    pub grant: Pubkey,
+    pub beneficiary: Pubkey,
+    pub vested_amount: u64,
+    pub returned_amount: u64,
+}
+
+#[event]
+pub struct DenylistUpdated {
+    pub wallet: Pubkey,
+    pub denied: bool,
+    pub reason: u8,
+}
+
+#[event]
+pub struct KycProviderUpdated {
+    pub kyc_provider: Pubkey,
+    pub authority: Pubkey,
+    pub active: bool,
+}
+
+#[event]
+pub struct CredentialUpdated {
+    pub wallet: Pubkey,
+    pub provider: Pubkey,
+    pub status: KycStatus,
+    pub tier: u8,
+    pub country: [u8; 2],
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct FloatReserved {
+    pub asset: Pubkey,
+    pub amount: u64,
+    pub free_float: u64,
+    pub minted_supply: u64,
+}
+
+#[event]
+pub struct FloatReleased {
+    pub asset: Pubkey,
+    pub amount: u64,
+    pub free_float: u64,
+    pub minted_supply: u64,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Invalid free float amount")]
+    InvalidFreeFloat,
+    #[msg("Not enough free float")]
+    InsufficientFloat,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Too many quote mints")]
+    TooManyQuoteMints,
+    #[msg("Platform and issuer fee basis points exceed 10000")]
+    InvalidFeeBps,
+    #[msg("Issuer fee exceeds the platform cap")]
+    IssuerFeeTooHigh,
+    #[msg("Quote mint is not allowed by the platform")]
+    QuoteMintNotAllowed,
+    #[msg("Platform is paused")]
+    PlatformPaused,
+    #[msg("Asset name is empty or too long")]
+    InvalidName,
+    #[msg("Ticker is empty or too long")]
+    InvalidTicker,
+    #[msg("Country must be an ISO 3166-1 alpha-2 code")]
+    InvalidCountryCode,
+    #[msg("Metadata URI is too long")]
+    UriTooLong,
+    #[msg("Operation not allowed in the current asset status")]
+    InvalidAssetStatus,
+    #[msg("Asset status transition is not allowed")]
+    InvalidStatusTransition,
+    #[msg("Account is not a legacy asset for this issuer and mint")]
+    InvalidLegacyAsset,
+    #[msg("Minting would exceed total supply")]
+    SupplyCapExceeded,
+    #[msg("No retained allocation left to mint")]
+    NothingToRetain,
+    #[msg("Shares are still locked")]
+    StillLocked,
+    #[msg("Allocation already released")]
+    AlreadyReleased,
+    #[msg("Vesting schedule is invalid")]
+    InvalidVestingSchedule,
+    #[msg("Nothing has vested since the last release")]
+    NothingToRelease,
+    #[msg("Vesting grant is not revocable")]
+    NotRevocable,
+    #[msg("Vesting grant already revoked")]
+    AlreadyRevoked,
+    #[msg("KYC provider is not active")]
+    KycProviderInactive,
+    #[msg("Too many disabled KYC providers")]
+    TooManyDisabledKycProviders,
+    #[msg("Too many countries in jurisdiction policy")]
+    TooManyJurisdictions,
+    #[msg("Source and destination must differ")]
+    InvalidForcedTransfer,
+    #[msg("Holder has not approved mint_auth as delegate")]
+    DelegateNotApproved,
+    #[msg("Source account does not hold enough shares")]
+    InsufficientShares,
+    #[msg("Account holds recalled shares and must stay frozen")]
+    RecallPending,
+    #[msg("Lockup period must not be negative")]
+    InvalidLockupPeriod,
+    #[msg("Documents hash does not match the published offering documents")]
+    DocumentsHashMismatch,
+}
+
+fn share_metadata(asset: &Asset) -> DataV2 {
+    DataV2 {
+        name: asset.metadata.name.clone(),
+        symbol: asset.metadata.ticker.clone(),
+        uri: asset.metadata.uri.clone(),
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: None,
+        uses: None,
+    }
+}
+
+/// ISO 3166-1 alpha-2 shape: two uppercase ASCII letters.
+fn is_country_code(code: &[u8; 2]) -> bool {
+    code.iter().all(u8::is_ascii_uppercase)
+}
+
+/// Buys and sells charge both fees on the same total, so the platform fee and
+/// the issuer fee cap together must stay within `MAX_FEE_BPS`.
+fn check_fee_sum(fee_bps: u16, issuer_fee_bps: u16) -> Result<()> {
+    require!(
+        fee_bps as u32 + issuer_fee_bps as u32 <= MAX_FEE_BPS as u32,
+        ErrorCode::InvalidFeeBps
+    );
+    Ok(())
+}
+
+/// Creates the share mint with `mint_auth` as mint and freeze authority.
+/// Token-2022 mints also get the transfer hook and permanent delegate.
+fn init_share_mint(accounts: &CreateAsset, decimals: u8) -> Result<()> {
+    let mint = accounts.asset_mint.to_account_info();
+    let token_program = accounts.share_token_program.to_account_info();
+    let mint_authority = accounts.mint_authority.key();
+    let is_token_2022 = token_program.key() == Token2022::id();
+
+    let extensions: &[ExtensionType] = if is_token_2022 {
+        &[
+            ExtensionType::TransferHook,
+            ExtensionType::PermanentDelegate,
+        ]
+    } else {
+        &[]
+    };
+    let space =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions)?;
+    anchor_lang::system_program::create_account(
+        CpiContext::new(
+            accounts.system_program.to_account_info(),
+            anchor_lang::system_program::CreateAccount {
+                from: accounts.issuer.to_account_info(),
+                to: mint.clone(),
+            },
+        ),
+        Rent::get()?.minimum_balance(space),
+        space as u64,
+        token_program.key,
+    )?;
+
+    if is_token_2022 {
+        token_interface::transfer_hook_initialize(
+            CpiContext::new(
+                token_program.clone(),
+                token_interface::TransferHookInitialize {
+                    token_program_id: token_program.clone(),
+                    mint: mint.clone(),
+                },
+            ),
+            Some(mint_authority),
+            Some(TRANSFER_HOOK_PROGRAM_ID),
+        )?;
+        token_interface::permanent_delegate_initialize(
+            CpiContext::new(
+                token_program.clone(),
+                token_interface::PermanentDelegateInitialize {
+                    token_program_id: token_program.clone(),
+                    mint: mint.clone(),
+                },
+            ),
+            &mint_authority,
+        )?;
+    }
+
+    token_interface::initialize_mint2(
+        CpiContext::new(token_program, token_interface::InitializeMint2 { mint }),
+        decimals,
+        &mint_authority,
+        Some(&mint_authority),
+    )
+}
+
+/// Whether `mint_auth` can burn `amount` recalled shares from `source`: as
+/// the Token-2022 permanent delegate, or through a legacy approval.
+fn can_recall_burn(
+    mint: &AccountInfo,
+    source: &InterfaceAccount<TokenAccount>,
+    mint_authority: &Pubkey,
+    amount: u64,
+) -> bool {
+    is_permanent_delegate(mint, mint_authority)
+        || (source.delegate == Some(*mint_authority).into() && source.delegated_amount >= amount)
+}
+
+/// Freezes or thaws a share account through the `mint_auth` freeze authority.
+fn set_frozen<'info>(
+    token_program: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    mint_authority: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+    frozen: bool,
+) -> Result<()> {
+    if frozen {
+        token_interface::freeze_account(CpiContext::new_with_signer(
+            token_program.clone(),
+            FreezeAccount {
+                account: account.clone(),
+                mint: mint.clone(),
+                authority: mint_authority.clone(),
+            },
+            signer,
+        ))
+    } else {
+        token_interface::thaw_account(CpiContext::new_with_signer(
+            token_program.clone(),
+            ThawAccount {
+                account: account.clone(),
+                mint: mint.clone(),
+                authority: mint_authority.clone(),
+            },
+            signer,
+        ))
+    }
+}
+
+/// True while a recall lock still holds shares `settle_recall` has not moved.
+fn recall_pending(recall_lock: &AccountInfo) -> Result<bool> {
+    if recall_lock.data_is_empty() {
+        return Ok(false);
+    }
+    let lock = RecallLock::try_deserialize(&mut &recall_lock.data.borrow()[..])?;
+    Ok(lock.amount > 0)
+}
+
+/// Token-2022 share mints carry a permanent delegate; legacy mints never do.
+fn is_permanent_delegate(mint: &AccountInfo, delegate: &Pubkey) -> bool {
+    mint.owner == &Token2022::id()
+        && get_mint_extension_data::<
+            spl_token_2022::extension::permanent_delegate::PermanentDelegate,
+        >(mint)
+            .is_ok_and(|extension| Option::<Pubkey>::from(extension.delegate) == Some(*delegate))
+}
+
+/// `transfer_checked` for share tokens. Unlike the anchor-spl wrapper it
+/// forwards `remaining_accounts`, which Token-2022 needs to resolve the
+/// transfer hook's extra accounts. Legacy mints ignore them.
+fn transfer_shares<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    spl_token_2022::onchain::invoke_transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+        &ctx.remaining_accounts,
+        amount,
+        decimals,
+        ctx.signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(total_amount: u64, start_ts: i64, cliff_ts: i64, end_ts: i64) -> VestingGrant {
+        VestingGrant {
+            asset: Pubkey::default(),
+            beneficiary: Pubkey::default(),
+            grant_id: 0,
+            category: VestingCategory::Team,
+            total_amount,
+            released_amount: 0,
+            schedule: VestingSchedule {
+                start_ts,
+                cliff_ts,
+                end_ts,
+            },
+            revocable: true,
+            revoked: false,
+            bump: 0,
+            bump_vault: 0,
+        }
+    }
+
+    #[test]
+    fn nothing_vests_before_the_cliff() {
+        let grant = grant(1_000, 100, 200, 1_100);
+        assert_eq!(grant.vested_amount(0).unwrap(), 0);
+        assert_eq!(grant.vested_amount(199).unwrap(), 0);
+    }
+
+    #[test]
+    fn vests_linearly_from_start_after_the_cliff() {
+        let grant = grant(1_000, 100, 200, 1_100);
+        assert_eq!(grant.vested_amount(200).unwrap(), 100);
+        assert_eq!(grant.vested_amount(600).unwrap(), 500);
+        assert_eq!(grant.vested_amount(1_099).unwrap(), 999);
+    }
+
+    #[test]
+    fn everything_vests_at_the_end() {
+        let grant = grant(1_000, 100, 200, 1_100);
+        assert_eq!(grant.vested_amount(1_100).unwrap(), 1_000);
+        assert_eq!(grant.vested_amount(i64::MAX).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn vested_amount_rounds_down_without_overflow() {
+        let grant = grant(u64::MAX, 0, 0, 3);
+        assert_eq!(grant.vested_amount(1).unwrap(), u64::MAX / 3);
+        assert_eq!(grant.vested_amount(2).unwrap(), u64::MAX / 3 * 2);
+    }
+
+    #[test]
+    fn revoked_grant_keeps_its_frozen_total() {
+        let mut grant = grant(1_000, 100, 200, 1_100);
+        grant.total_amount = grant.vested_amount(600).unwrap();
+        grant.revoked = true;
+        assert_eq!(grant.vested_amount(700).unwrap(), 500);
+        assert_eq!(grant.vested_amount(0).unwrap(), 500);
+    }
+
+    #[test]
+    fn platform_and_issuer_fees_share_one_budget() {
+        assert!(check_fee_sum(5_000, 5_000).is_ok());
+        assert!(check_fee_sum(0, MAX_FEE_BPS).is_ok());
+        assert!(check_fee_sum(5_001, 5_000).is_err());
+        assert!(check_fee_sum(MAX_FEE_BPS, MAX_FEE_BPS).is_err());
+    }
+
+    #[test]
+    fn schedule_requires_ordered_dates() {
+        assert!(grant(1, 0, 0, 1).schedule.validate().is_ok());
+        assert!(grant(1, 0, 1, 1).schedule.validate().is_ok());
+        assert!(grant(1, 1, 0, 2).schedule.validate().is_err());
+        assert!(grant(1, 0, 3, 2).schedule.validate().is_err());
+        assert!(grant(1, 5, 5, 5).schedule.validate().is_err());
+    }
+}
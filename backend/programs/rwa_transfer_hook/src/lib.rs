@@ -0,0 +1,359 @@
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use rwa_market::program::RwaMarket;
+use rwa_market::Position;
+use rwa_registry::program::RwaRegistry;
+use rwa_registry::{Asset, Credential, DisabledKycProviders};
+use spl_discriminator::SplDiscriminate;
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, pubkey_data::PubkeyData, seeds::Seed, state::ExtraAccountMetaList,
+};
+use spl_transfer_hook_interface::instruction::{
+    ExecuteInstruction, InitializeExtraAccountMetaListInstruction,
+    UpdateExtraAccountMetaListInstruction,
+};
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnU");
+
+/// Offset of the owner pubkey in an SPL token account.
+const TOKEN_ACCOUNT_OWNER_OFFSET: u8 = 32;
+
+#[program]
+pub mod rwa_transfer_hook {
+    use super::*;
+
+    /// Writes the extra accounts Token-2022 resolves for every transfer of a
+    /// registry share mint. Permissionless: the list is the same for all mints.
+    #[instruction(discriminator = InitializeExtraAccountMetaListInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+    ) -> Result<()> {
+        ExtraAccountMetaList::init::<ExecuteInstruction>(
+            &mut ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?,
+            &extra_account_metas()?,
+        )?;
+        Ok(())
+    }
+
+    /// Rewrites a mint's list with the current extra accounts, e.g. after the
+    /// disabled KYC provider list was added. Permissionless like the initializer.
+    #[instruction(discriminator = UpdateExtraAccountMetaListInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn update_extra_account_meta_list(ctx: Context<UpdateExtraAccountMetaList>) -> Result<()> {
+        let metas = extra_account_metas()?;
+        let list = ctx.accounts.extra_account_meta_list.to_account_info();
+        let space = ExtraAccountMetaList::size_of(metas.len())?;
+        let lamports = Rent::get()?.minimum_balance(space);
+        if list.lamports() < lamports {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: list.clone(),
+                    },
+                ),
+                lamports - list.lamports(),
+            )?;
+        }
+        list.resize(space)?;
+
+        ExtraAccountMetaList::update::<ExecuteInstruction>(
+            &mut list.try_borrow_mut_data()?,
+            &metas,
+        )?;
+        Ok(())
+    }
+
+    /// Called by Token-2022 on every share transfer. Both sides must be
+    /// cleared: the issuer, a registry or market escrow, or a wallet with a valid
+    /// credential from a country the asset's jurisdiction policy allows.
+    /// Payouts from market escrows skip the receiver check: rwa_market checks
+    /// buyers when they order or swap, and lets liquidity providers exit
+    /// without a current credential.
+    /// The sender may not dip into shares still inside the purchase lockup.
+    #[instruction(discriminator = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn transfer_hook(ctx: Context<TransferHook>, _amount: u64) -> Result<()> {
+        let asset = &ctx.accounts.asset;
+        let now = Clock::get()?.unix_timestamp;
+        let disabled = disabled_providers(&ctx.accounts.disabled_kyc_providers)?;
+
+        require!(
+            is_cleared(
+                asset,
+                &ctx.accounts.source_owner,
+                &ctx.accounts.source_credential,
+                &disabled,
+                now
+            )?,
+            HookError::SenderNotCleared
+        );
+        require!(
+            ctx.accounts.source_owner.owner == &rwa_market::ID
+                || is_cleared(
+                    asset,
+                    &ctx.accounts.destination_owner,
+                    &ctx.accounts.destination_credential,
+                    &disabled,
+                    now
+                )?,
+            HookError::ReceiverNotCleared
+        );
+
+        // Token-2022 runs the hook after debiting the source, so its balance
+        // is already what the sender keeps.
+        require!(
+            ctx.accounts.source_token.amount >= locked_amount(&ctx.accounts.source_position, now)?,
+            HookError::SharesLocked
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: TLV account written by the handler
+    #[account(
+        init,
+        payer = payer,
+        space = ExtraAccountMetaList::size_of(extra_account_metas()?.len())?,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// Only share mints registered as assets get a list
+    #[account(
+        seeds = [b"asset", mint.key().as_ref()],
+        bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub asset: Account<'info, Asset>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account order is fixed by the transfer hook interface (0-4) and
+/// `extra_account_metas` (5-12).
+#[derive(Accounts)]
+pub struct UpdateExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: TLV account rewritten by the handler
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    #[account(token::mint = mint)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: source owner or delegate, authorized by Token-2022
+    pub authority: UncheckedAccount<'info>,
+    /// CHECK: validated by seeds
+    #[account(seeds = [b"extra-account-metas", mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+
+    pub registry_program: Program<'info, RwaRegistry>,
+    #[account(
+        seeds = [b"asset", mint.key().as_ref()],
+        bump = asset.bump_asset,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub asset: Account<'info, Asset>,
+    /// CHECK: owner of `source_token`
+    #[account(address = source_token.owner)]
+    pub source_owner: UncheckedAccount<'info>,
+    /// CHECK: registry credential PDA, may not exist
+    #[account(
+        seeds = [b"credential", source_token.owner.as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub source_credential: UncheckedAccount<'info>,
+    /// CHECK: owner of `destination_token`
+    #[account(address = destination_token.owner)]
+    pub destination_owner: UncheckedAccount<'info>,
+    /// CHECK: registry credential PDA, may not exist
+    #[account(
+        seeds = [b"credential", destination_token.owner.as_ref()],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub destination_credential: UncheckedAccount<'info>,
+
+    pub market_program: Program<'info, RwaMarket>,
+    /// CHECK: market position PDA of the source owner, may not exist
+    #[account(
+        seeds = [b"position", asset.key().as_ref(), source_token.owner.as_ref()],
+        bump,
+        seeds::program = rwa_market::ID,
+    )]
+    pub source_position: UncheckedAccount<'info>,
+    /// CHECK: registry list of disabled KYC providers, may not exist
+    #[account(
+        seeds = [b"disabled_kyc_providers"],
+        bump,
+        seeds::program = rwa_registry::ID,
+    )]
+    pub disabled_kyc_providers: UncheckedAccount<'info>,
+}
+
+#[error_code]
+pub enum HookError {
+    #[msg("Sender is not cleared to hold this asset")]
+    SenderNotCleared,
+    #[msg("Receiver is not cleared to hold this asset")]
+    ReceiverNotCleared,
+    #[msg("Shares are still inside the lockup period")]
+    SharesLocked,
+}
+
+/// Extra accounts appended to `Execute`, in `TransferHook` order.
+fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    let owner_credential = |token_account_index: u8| -> Result<ExtraAccountMeta> {
+        Ok(ExtraAccountMeta::new_external_pda_with_seeds(
+            5,
+            &[
+                Seed::Literal {
+                    bytes: b"credential".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: token_account_index,
+                    data_index: TOKEN_ACCOUNT_OWNER_OFFSET,
+                    length: 32,
+                },
+            ],
+            false,
+            false,
+        )?)
+    };
+    let owner = |token_account_index: u8| -> Result<ExtraAccountMeta> {
+        Ok(ExtraAccountMeta::new_with_pubkey_data(
+            &PubkeyData::AccountData {
+                account_index: token_account_index,
+                data_index: TOKEN_ACCOUNT_OWNER_OFFSET,
+            },
+            false,
+            false,
+        )?)
+    };
+
+    Ok(vec![
+        // 5: registry program
+        ExtraAccountMeta::new_with_pubkey(&rwa_registry::ID, false, false)?,
+        // 6: asset
+        ExtraAccountMeta::new_external_pda_with_seeds(
+            5,
+            &[
+                Seed::Literal {
+                    bytes: b"asset".to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+            ],
+            false,
+            false,
+        )?,
+        // 7-8: source owner and credential
+        owner(0)?,
+        owner_credential(0)?,
+        // 9-10: destination owner and credential
+        owner(2)?,
+        owner_credential(2)?,
+        // 11: market program
+        ExtraAccountMeta::new_with_pubkey(&rwa_market::ID, false, false)?,
+        // 12: source position
+        ExtraAccountMeta::new_external_pda_with_seeds(
+            11,
+            &[
+                Seed::Literal {
+                    bytes: b"position".to_vec(),
+                },
+                Seed::AccountKey { index: 6 },
+                Seed::AccountData {
+                    account_index: 0,
+                    data_index: TOKEN_ACCOUNT_OWNER_OFFSET,
+                    length: 32,
+                },
+            ],
+            false,
+            false,
+        )?,
+        // 13: disabled KYC providers
+        ExtraAccountMeta::new_external_pda_with_seeds(
+            5,
+            &[Seed::Literal {
+                bytes: b"disabled_kyc_providers".to_vec(),
+            }],
+            false,
+            false,
+        )?,
+    ])
+}
+
+/// Issuer, registry-held escrows (retained stake, vesting vaults) and market
+/// escrows (order book) are always cleared; anyone else needs a current
+/// credential from an allowed country whose provider is not disabled.
+fn is_cleared(
+    asset: &Asset,
+    owner: &AccountInfo,
+    credential: &AccountInfo,
+    disabled: &[Pubkey],
+    now: i64,
+) -> Result<bool> {
+    if owner.key() == asset.issuer
+        || owner.owner == &rwa_registry::ID
+        || owner.owner == &rwa_market::ID
+    {
+        return Ok(true);
+    }
+    if credential.owner != &rwa_registry::ID || credential.data_is_empty() {
+        return Ok(false);
+    }
+
+    let credential = Credential::try_deserialize(&mut &credential.try_borrow_data()?[..])?;
+    Ok(credential.is_current(now)
+        && !disabled.contains(&credential.provider)
+        && asset.jurisdiction.allows(&credential.country))
+}
+
+/// Disabled KYC providers; none if the registry never disabled one.
+fn disabled_providers(list: &AccountInfo) -> Result<Vec<Pubkey>> {
+    if list.owner != &rwa_registry::ID || list.data_is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let list = DisabledKycProviders::try_deserialize(&mut &list.try_borrow_data()?[..])?;
+    Ok(list.providers)
+}
+
+/// Locked purchase lots of a market position; zero if the holder never bought
+/// through the market.
+fn locked_amount(position: &AccountInfo, now: i64) -> Result<u64> {
+    if position.owner != &rwa_market::ID || position.data_is_empty() {
+        return Ok(0);
+    }
+
+    let position = Position::try_deserialize(&mut &position.try_borrow_data()?[..])?;
+    Ok(position.locked_amount(now))
+}
@@ -0,0 +1,9 @@
+#!/usr/bin/env bash
+# Dumps the mainnet programs that Anchor.toml loads into the local test
+# validator via [[test.genesis]]. Run once before `anchor test`.
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+mkdir -p tests/fixtures
+
+solana program dump -u m metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s tests/fixtures/mpl_token_metadata.so
@@ -0,0 +1,246 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccount,
+  createMint,
+  getAccount,
+  getMint,
+} from "@solana/spl-token";
+import { expect } from "chai";
+import { RwaRegistry } from "../target/types/rwa_registry";
+
+const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
+
+// Borsh string: u32 length, then bytes. Metaplex pads name/symbol/uri with NULs.
+function readString(data: Buffer, offset: number): [string, number] {
+  const len = data.readUInt32LE(offset);
+  const value = data.subarray(offset + 4, offset + 4 + len).toString("utf8").replace(/\0+$/, "");
+  return [value, offset + 4 + len];
+}
+
+describe("rwa_registry", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.RwaRegistry as Program<RwaRegistry>;
+  const admin = provider.wallet as anchor.Wallet;
+
+  const [platformConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("platform_config")],
+    program.programId
+  );
+  const [programData] = PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_ID
+  );
+
+  let usdcMint: PublicKey;
+
+  before(async () => {
+    usdcMint = await createMint(provider.connection, admin.payer, admin.publicKey, null, 6);
+
+    await program.methods
+      .initializePlatform([usdcMint], 50, 500, admin.publicKey)
+      .accountsPartial({
+        admin: admin.publicKey,
+        platformConfig,
+        program: program.programId,
+        programData,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  it("creates an asset and its share mint metadata", async () => {
+    const assetMint = Keypair.generate();
+    const metadata = {
+      name: "Riverside Offices",
+      ticker: "RVSD",
+      category: { realEstate: {} },
+      country: Array.from(Buffer.from("DE")),
+      uri: "https://example.com/rvsd.json",
+    };
+
+    const [asset] = PublicKey.findProgramAddressSync(
+      [Buffer.from("asset"), assetMint.publicKey.toBuffer()],
+      program.programId
+    );
+    const [mintAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_auth"), assetMint.publicKey.toBuffer()],
+      program.programId
+    );
+    const [shareMetadata] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), assetMint.publicKey.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+
+    await program.methods
+      .createAsset(new anchor.BN(1_000_000), new anchor.BN(1_000), new anchor.BN(600), 0, metadata)
+      .accountsPartial({
+        issuer: admin.publicKey,
+        platformConfig,
+        assetMint: assetMint.publicKey,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        shareTokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([assetMint])
+      .rpc();
+
+    const assetAccount = await program.account.asset.fetch(asset);
+    expect(assetAccount.assetMint.toBase58()).to.equal(assetMint.publicKey.toBase58());
+    expect(assetAccount.metadata.ticker).to.equal("RVSD");
+
+    await program.methods
+      .createShareMetadata()
+      .accountsPartial({
+        issuer: admin.publicKey,
+        asset,
+        assetMint: assetMint.publicKey,
+        mintAuthority,
+        shareMetadata,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      })
+      .rpc();
+
+    const info = await provider.connection.getAccountInfo(shareMetadata);
+    expect(info).to.not.be.null;
+    expect(info!.owner.toBase58()).to.equal(TOKEN_METADATA_PROGRAM_ID.toBase58());
+
+    // key (1) | update_authority (32) | mint (32) | name | symbol | uri
+    const data = info!.data;
+    expect(new PublicKey(data.subarray(1, 33)).toBase58()).to.equal(mintAuthority.toBase58());
+    expect(new PublicKey(data.subarray(33, 65)).toBase58()).to.equal(assetMint.publicKey.toBase58());
+    const [name, symbolOffset] = readString(data, 65);
+    const [symbol, uriOffset] = readString(data, symbolOffset);
+    const [uri] = readString(data, uriOffset);
+    expect(name).to.equal(metadata.name);
+    expect(symbol).to.equal(metadata.ticker);
+    expect(uri).to.equal(metadata.uri);
+  });
+
+  it("recalls legacy shares without a delegate only once they are burned", async () => {
+    const assetMint = Keypair.generate();
+    const [asset] = PublicKey.findProgramAddressSync(
+      [Buffer.from("asset"), assetMint.publicKey.toBuffer()],
+      program.programId
+    );
+    const [roles] = PublicKey.findProgramAddressSync(
+      [Buffer.from("roles"), asset.toBuffer()],
+      program.programId
+    );
+    const [mintAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_auth"), assetMint.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createAsset(new anchor.BN(1_000), new anchor.BN(1_000), new anchor.BN(600), 0, {
+        name: "Harbour Warehouse",
+        ticker: "HRBW",
+        category: { realEstate: {} },
+        country: Array.from(Buffer.from("NL")),
+        uri: "https://example.com/hrbw.json",
+      })
+      .accountsPartial({
+        issuer: admin.publicKey,
+        platformConfig,
+        assetMint: assetMint.publicKey,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        shareTokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([assetMint])
+      .rpc();
+
+    // The issuer's retained 400 shares land in an ordinary, non-delegated account
+    const source = await createAssociatedTokenAccount(
+      provider.connection,
+      admin.payer,
+      assetMint.publicKey,
+      admin.publicKey
+    );
+    const destination = await createAssociatedTokenAccount(
+      provider.connection,
+      admin.payer,
+      assetMint.publicKey,
+      Keypair.generate().publicKey
+    );
+    await program.methods
+      .mintRetained(new anchor.BN(0))
+      .accountsPartial({ issuer: admin.publicKey, asset, assetMint: assetMint.publicKey })
+      .rpc();
+    await program.methods
+      .releaseRetained()
+      .accountsPartial({
+        issuer: admin.publicKey,
+        asset,
+        assetMint: assetMint.publicKey,
+        issuerShares: source,
+      })
+      .rpc();
+
+    const [recallLock] = PublicKey.findProgramAddressSync(
+      [Buffer.from("recall"), source.toBuffer()],
+      program.programId
+    );
+    const recallAccounts = {
+      authority: admin.publicKey,
+      asset,
+      roles,
+      assetMint: assetMint.publicKey,
+      mintAuthority,
+      sourceShares: source,
+      destinationShares: destination,
+      recallLock,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+
+    await program.methods
+      .forcedTransfer(new anchor.BN(150), 1, Array(32).fill(0))
+      .accountsPartial(recallAccounts)
+      .rpc();
+
+    // Frozen in place: nothing minted, nothing burned
+    expect((await getMint(provider.connection, assetMint.publicKey)).supply.toString()).to.equal("400");
+    const frozen = await getAccount(provider.connection, source);
+    expect(frozen.isFrozen).to.equal(true);
+    expect(frozen.amount.toString()).to.equal("400");
+    expect((await getAccount(provider.connection, destination)).amount.toString()).to.equal("0");
+    expect((await program.account.recallLock.fetch(recallLock)).amount.toNumber()).to.equal(150);
+
+    const thawAccounts = {
+      authority: admin.publicKey,
+      asset,
+      roles,
+      assetMint: assetMint.publicKey,
+      mintAuthority,
+      holderShares: source,
+      recallLock,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    let thawError: unknown;
+    try {
+      await program.methods.thawHolder(1).accountsPartial(thawAccounts).rpc();
+    } catch (err) {
+      thawError = err;
+    }
+    expect(String(thawError)).to.include("RecallPending");
+
+    await program.methods
+      .settleRecall()
+      .accountsPartial({ ...recallAccounts, holder: admin.publicKey })
+      .rpc();
+
+    expect((await getMint(provider.connection, assetMint.publicKey)).supply.toString()).to.equal("400");
+    expect((await getAccount(provider.connection, source)).amount.toString()).to.equal("250");
+    expect((await getAccount(provider.connection, destination)).amount.toString()).to.equal("150");
+    expect(await provider.connection.getAccountInfo(recallLock)).to.be.null;
+
+    await program.methods.thawHolder(1).accountsPartial(thawAccounts).rpc();
+    expect((await getAccount(provider.connection, source)).isFrozen).to.equal(false);
+  });
+});
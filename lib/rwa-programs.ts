@@ -0,0 +1,47 @@
+import { Connection, PublicKey } from '@solana/web3.js';
+import { AnchorProvider, BN, Idl, Program } from '@coral-xyz/anchor';
+import rwaMarketIdl from './idl/rwa_market.json';
+import rwaRegistryIdl from './idl/rwa_registry.json';
+
+// IDL из backend/target/idl после `anchor build`, обновлять вместе с программами
+export const MARKET_PROGRAM_ID = new PublicKey(rwaMarketIdl.address);
+export const REGISTRY_PROGRAM_ID = new PublicKey(rwaRegistryIdl.address);
+
+const BPS_DENOMINATOR = new BN(10_000);
+
+export function getPrograms(provider: AnchorProvider) {
+  return {
+    market: new Program(rwaMarketIdl as Idl, provider),
+    registry: new Program(rwaRegistryIdl as Idl, provider),
+  };
+}
+
+export function findRegistryPda(seeds: (Buffer | Uint8Array)[]): PublicKey {
+  return PublicKey.findProgramAddressSync(seeds, REGISTRY_PROGRAM_ID)[0];
+}
+
+export function findMarketPda(seeds: (Buffer | Uint8Array)[]): PublicKey {
+  return PublicKey.findProgramAddressSync(seeds, MARKET_PROGRAM_ID)[0];
+}
+
+/** Комиссия так же, как в программе: `total * bps / 10_000` с округлением вниз */
+export function feeAmount(total: BN, bps: number): BN {
+  return total.mul(new BN(bps)).div(BPS_DENOMINATOR);
+}
+
+/** Комиссия эмитента, урезанная до лимита платформы, как `PlatformConfig::issuer_fee_bps` */
+export function issuerFeeBps(
+  asset: { issuerFeeBps: number },
+  platformConfig: { maxIssuerFeeBps: number }
+): number {
+  return Math.min(asset.issuerFeeBps, platformConfig.maxIssuerFeeBps);
+}
+
+/** Token program, которой принадлежит mint (SPL Token или Token-2022) */
+export async function getMintTokenProgram(connection: Connection, mint: PublicKey): Promise<PublicKey> {
+  const info = await connection.getAccountInfo(mint);
+  if (!info) {
+    throw new Error(`Account not found: ${mint.toBase58()}`);
+  }
+  return info.owner;
+}